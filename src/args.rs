@@ -0,0 +1,457 @@
+use util::point3::Point3;
+use util::vector3::Vector3;
+use util::color::Color;
+use util::linear_color::LinearColor;
+use util::texture::{Filter, Wrap};
+use util::camera::View;
+use util::world::Shading;
+use util::light::{Light, Reflection, Attenuation};
+
+// 動作モード
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Command {
+    // ウィンドウに表示
+    Viewer,
+    // PNGファイルに書き出す
+    Render,
+    // 変換と描画の時間を測る
+    Bench
+}
+
+// コマンドライン引数
+#[derive(Debug, Clone)]
+pub struct Args {
+    pub command: Command,
+    pub help: bool,
+    pub model: String,
+    pub output: String,
+    pub width: i32,
+    pub height: i32,
+    pub position: Option<Point3>,
+    pub look_at: Option<Point3>,
+    pub fov: f64,
+    pub ortho: bool,
+    pub view: Option<View>,
+    pub fit: f64,
+    pub yaw: i32,
+    pub speed: i32,
+    pub frames: usize,
+    pub shading: Shading,
+    pub reflection: Reflection,
+    pub lights: Vec<Light>,
+    pub background: Color,
+    pub texture: Option<String>,
+    pub filter: Filter,
+    pub wrap: Wrap
+}
+
+impl Args {
+    pub fn new() -> Args {
+        Args {
+            command: Command::Viewer,
+            help: false,
+            model: "miku.obj".to_string(),
+            output: "out.png".to_string(),
+            width: 640,
+            height: 480,
+            position: None,
+            look_at: None,
+            fov: 45.0f64,
+            ortho: false,
+            view: None,
+            fit: 0.8f64,
+            yaw: 220,
+            speed: 3,
+            frames: 100,
+            shading: Shading::Gouraud,
+            reflection: Reflection::BlinnPhong,
+            lights: Vec::new(),
+            background: Color::new(0, 0, 0),
+            texture: None,
+            filter: Filter::Bilinear,
+            wrap: Wrap::Repeat
+        }
+    }
+
+    // 引数(プログラム名を除く)を解釈する
+    pub fn parse(args: &[String]) -> Result<Args, String> {
+        let mut result = Args::new();
+        let mut iter = args.iter().peekable();
+        match iter.peek().map(|s| s.as_str()) {
+            Some("render") => result.command = Command::Render,
+            Some("bench") => result.command = Command::Bench,
+            _ => ()
+        }
+        if result.command != Command::Viewer {
+            iter.next();
+        }
+        let mut model: Option<String> = None;
+        while let Some(arg) = iter.next() {
+            let mut value = || iter.next().map(|s| s.as_str()).ok_or_else(|| format!("{} needs a value", arg));
+            match arg.as_str() {
+                "-h" | "--help" => result.help = true,
+                "-o" | "--output" => result.output = value()?.to_string(),
+                "--size" => {
+                    let (width, height) = parse_size(value()?)?;
+                    result.width = width;
+                    result.height = height;
+                }
+                "--position" => result.position = Some(parse_point(value()?)?),
+                "--look-at" => result.look_at = Some(parse_point(value()?)?),
+                "--fov" => {
+                    result.fov = parse_number(value()?, "fov")?;
+                    if !(result.fov > 0.0f64 && result.fov < 180.0f64) {
+                        return Err(format!("invalid fov '{}' (expected 0 < DEGREES < 180)", result.fov));
+                    }
+                }
+                "--ortho" => result.ortho = true,
+                "--view" => result.view = Some(match value()? {
+                    "front" => View::Front,
+                    "side" => View::Side,
+                    "top" => View::Top,
+                    "iso" => View::Iso,
+                    s => return Err(format!("invalid view '{}' (expected front, side, top or iso)", s))
+                }),
+                "--fit" => {
+                    result.fit = parse_number(value()?, "fit")?;
+                    if !(result.fit > 0.0f64 && result.fit <= 1.0f64) {
+                        return Err(format!("invalid fit '{}' (expected 0 < FRACTION <= 1)", result.fit));
+                    }
+                }
+                "--yaw" => result.yaw = parse_number(value()?, "yaw")?,
+                "--speed" => result.speed = parse_number(value()?, "speed")?,
+                "--frames" => result.frames = parse_number(value()?, "frames")?,
+                "--shading" => result.shading = match value()? {
+                    "flat" => Shading::Flat,
+                    "smooth" => Shading::Gouraud,
+                    "phong" => Shading::Phong,
+                    s => return Err(format!("invalid shading '{}' (expected flat, smooth or phong)", s))
+                },
+                "--specular" => result.reflection = match value()? {
+                    "phong" => Reflection::Phong,
+                    "blinn" => Reflection::BlinnPhong,
+                    s => return Err(format!("invalid specular model '{}' (expected phong or blinn)", s))
+                },
+                "--light" => {
+                    let light = parse_light(value()?)?;
+                    result.lights.push(light);
+                }
+                "--background" => result.background = parse_color(value()?)?,
+                "--texture" => result.texture = Some(value()?.to_string()),
+                "--filter" => result.filter = match value()? {
+                    "nearest" => Filter::Nearest,
+                    "bilinear" => Filter::Bilinear,
+                    s => return Err(format!("invalid filter '{}' (expected nearest or bilinear)", s))
+                },
+                "--wrap" => result.wrap = match value()? {
+                    "repeat" => Wrap::Repeat,
+                    "clamp" => Wrap::Clamp,
+                    s => return Err(format!("invalid wrap mode '{}' (expected repeat or clamp)", s))
+                },
+                s if s.starts_with('-') => return Err(format!("unknown option '{}'", s)),
+                s => {
+                    if model.is_some() {
+                        return Err(format!("unexpected argument '{}'", s));
+                    }
+                    model = Some(s.to_string());
+                }
+            }
+        }
+        match model {
+            Some(model) => result.model = model,
+            // 書き出すときはモデルを省略できない
+            None if result.command == Command::Render && !result.help =>
+                return Err("render needs a MODEL.obj".to_string()),
+            None => ()
+        }
+        Ok(result)
+    }
+
+    // 使い方
+    pub fn usage() -> String {
+        [
+            "usage: rust3d [OPTIONS] [MODEL.obj]",
+            "       rust3d render [OPTIONS] MODEL.obj",
+            "       rust3d bench [OPTIONS] [MODEL.obj]",
+            "",
+            "commands:",
+            "  render                 draw without a window and write a PNG file",
+            "  bench                  measure vertex transform and drawing time",
+            "",
+            "options:",
+            "  -h, --help             print this help",
+            "  -o, --output FILE      PNG file to write in render mode (default: out.png)",
+            "  --size WxH             window or image size in pixels (default: 640x480)",
+            "  --position X,Y,Z       camera position (default: fitted to the model)",
+            "  --look-at X,Y,Z        point the camera looks at (default: 0,0,0)",
+            "  --fov DEGREES          vertical field of view (default: 45)",
+            "  --ortho                parallel projection without perspective",
+            "  --view VIEW            look from front, side, top or iso (default: front)",
+            "  --fit FRACTION         part of the view the model fills, 0 to 1 (default: 0.8)",
+            "  --yaw DEGREES          initial rotation around the Y axis (default: 220)",
+            "  --speed DEGREES        rotation per frame in the viewer (default: 3)",
+            "  --frames N             frames to measure in bench mode (default: 100)",
+            "  --shading MODE         flat, smooth or phong (per pixel) (default: smooth)",
+            "  --specular MODEL       highlight model: phong or blinn (default: blinn)",
+            "  --light LIGHT          add a light, replacing the default ones (repeatable):",
+            "                           ambient, directional:DX,DY,DZ, point:X,Y,Z or",
+            "                           spot:X,Y,Z:TX,TY,TZ:OUTER[,INNER] (degrees, INNER defaults",
+            "                           to 0.8 * OUTER), optionally followed by :COLOR, :INTENSITY",
+            "                           (default: white, 1 or 0.1 for ambient) and, for point and",
+            "                           spot, :C,L,Q attenuation 1/(C+L*d+Q*d^2) (default: 1,0,0)",
+            "  --background COLOR     background color as #rrggbb or R,G,B (default: #000000)",
+            "  --texture FILE         texture image used for every material",
+            "  --filter MODE          texture filter: nearest or bilinear (default: bilinear)",
+            "  --wrap MODE            texture wrap mode: repeat or clamp (default: repeat)",
+        ].join("\n")
+    }
+}
+
+fn parse_number<T: ::std::str::FromStr>(s: &str, name: &str) -> Result<T, String> {
+    s.parse::<T>().map_err(|_| format!("invalid {} '{}'", name, s))
+}
+
+// 640x480
+fn parse_size(s: &str) -> Result<(i32, i32), String> {
+    let xs: Vec<Option<i32>> = s.split('x').map(|x| x.parse::<i32>().ok()).collect();
+    match xs.as_slice() {
+        &[Some(w), Some(h)] if w > 0 && h > 0 => Ok((w, h)),
+        _ => Err(format!("invalid size '{}' (expected WIDTHxHEIGHT)", s))
+    }
+}
+
+// 0,-1,-100
+fn parse_point(s: &str) -> Result<Point3, String> {
+    let xs: Vec<Option<f64>> = s.split(',').map(|x| x.trim().parse::<f64>().ok()).collect();
+    match xs.as_slice() {
+        &[Some(x), Some(y), Some(z)] => Ok(Point3::new(x, y, z, 1.0f64)),
+        _ => Err(format!("invalid point '{}' (expected X,Y,Z)", s))
+    }
+}
+
+// #rrggbb または R,G,B
+fn parse_color(s: &str) -> Result<Color, String> {
+    let error = || format!("invalid color '{}' (expected #rrggbb or R,G,B)", s);
+    if s.starts_with('#') && s.len() == 7 && s.is_ascii() {
+        let c = |i: usize| u8::from_str_radix(&s[i..(i + 2)], 16).map_err(|_| error());
+        return Ok(Color::new(c(1)?, c(3)?, c(5)?));
+    }
+    let xs: Vec<Option<u8>> = s.split(',').map(|x| x.trim().parse::<u8>().ok()).collect();
+    match xs.as_slice() {
+        &[Some(r), Some(g), Some(b)] => Ok(Color::new(r, g, b)),
+        _ => Err(error())
+    }
+}
+
+// ambient、directional:DX,DY,DZ、point:X,Y,Z、spot:X,Y,Z:TX,TY,TZ:OUTER[,INNER]
+// 後ろに:COLOR:INTENSITYを付けられ、pointとspotはさらに:C,L,Q(減衰)を付けられる
+// 空の項目は省略したのと同じ
+fn parse_light(s: &str) -> Result<Light, String> {
+    let error = || format!("invalid light '{}' (expected ambient, directional:DX,DY,DZ, point:X,Y,Z \
+        or spot:X,Y,Z:TX,TY,TZ:OUTER[,INNER] with optional :COLOR:INTENSITY:C,L,Q)", s);
+    let xs: Vec<&str> = s.split(':').collect();
+    // 種類ごとの引数の数(残りは色、強さ、減衰)
+    let (used, attenuated) = match xs[0] {
+        "ambient" => (1, false),
+        "directional" => (2, false),
+        "point" => (2, true),
+        "spot" => (4, true),
+        _ => return Err(error())
+    };
+    let options = if attenuated { 3 } else { 2 };
+    if xs.len() < used || xs.len() > used + options {
+        return Err(error());
+    }
+    let option = |i: usize| xs.get(used + i).cloned().filter(|s| !s.is_empty());
+    let color = match option(0) {
+        Some(s) => LinearColor::from_srgb(&parse_color(s)?),
+        None => LinearColor::new(1.0f64, 1.0f64, 1.0f64, 1.0f64)
+    };
+    let intensity = match option(1) {
+        Some(s) => parse_number(s, "light intensity")?,
+        None if xs[0] == "ambient" => 0.1f64,
+        None => 1.0f64
+    };
+    let attenuation = match option(2) {
+        Some(s) => parse_attenuation(s)?,
+        None => Attenuation::none()
+    };
+    Ok(match xs[0] {
+        "ambient" => Light::ambient(&color, intensity),
+        "directional" => Light::directional(&Vector3::from(parse_point(xs[1])?), &color, intensity),
+        "point" => Light::point(&parse_point(xs[1])?, &attenuation, &color, intensity),
+        _ => {
+            let position = parse_point(xs[1])?;
+            let direction = Vector3::from(&parse_point(xs[2])? - &position);
+            let (outer, inner) = parse_spot_angles(xs[3])?;
+            Light::spot(&position, &direction, inner, outer, &attenuation, &color, intensity)
+        }
+    })
+}
+
+// C,L,Q(1 / (C + L * d + Q * d * d))
+fn parse_attenuation(s: &str) -> Result<Attenuation, String> {
+    let xs: Vec<Option<f64>> = s.split(',').map(|x| x.trim().parse::<f64>().ok()).collect();
+    match xs.as_slice() {
+        &[Some(c), Some(l), Some(q)] if c >= 0.0f64 && l >= 0.0f64 && q >= 0.0f64 && c + l + q > 0.0f64 =>
+            Ok(Attenuation::new(c, l, q)),
+        _ => Err(format!("invalid attenuation '{}' (expected CONSTANT,LINEAR,QUADRATIC)", s))
+    }
+}
+
+// OUTER[,INNER](度、INNERを省略するとOUTERの0.8倍)をラジアンで返す
+fn parse_spot_angles(s: &str) -> Result<(f64, f64), String> {
+    let xs: Vec<Option<f64>> = s.split(',').map(|x| x.trim().parse::<f64>().ok()).collect();
+    let (outer, inner) = match *xs.as_slice() {
+        [Some(outer)] => (outer, outer * 0.8f64),
+        [Some(outer), Some(inner)] => (outer, inner),
+        _ => (-1.0f64, -1.0f64)
+    };
+    if outer > 0.0f64 && outer < 180.0f64 && inner >= 0.0f64 && inner <= outer {
+        Ok((outer.to_radians(), inner.to_radians()))
+    } else {
+        Err(format!("invalid spot angle '{}' (expected OUTER[,INNER] with 0 <= INNER <= OUTER < 180)", s))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use util::light::LightKind;
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        Args::parse(&args.iter().map(|s| s.to_string()).collect::<Vec<String>>())
+    }
+
+    #[test]
+    fn command() {
+        {
+            let a = parse(&[]).unwrap();
+            assert_eq!(Command::Viewer, a.command);
+            assert_eq!("miku.obj", a.model);
+            let a = parse(&["render", "a.obj", "-o", "a.png", "--size", "800x600"]).unwrap();
+            assert_eq!(Command::Render, a.command);
+            assert_eq!("a.obj", a.model);
+            assert_eq!("a.png", a.output);
+            assert_eq!((800, 600), (a.width, a.height));
+            // renderはモデルを省略できない(--helpは除く)
+            assert!(parse(&["render", "-o", "a.png"]).unwrap_err().contains("MODEL.obj"));
+            assert!(parse(&["render", "--help"]).unwrap().help);
+            assert_eq!(Command::Bench, parse(&["bench", "--frames", "10"]).unwrap().command);
+            // オプションのあとにモデルを書いてもよい
+            let a = parse(&["--filter", "nearest", "--wrap", "clamp", "b.obj"]).unwrap();
+            assert_eq!("b.obj", a.model);
+            assert_eq!(Filter::Nearest, a.filter);
+            assert_eq!(Wrap::Clamp, a.wrap);
+            assert_eq!(Shading::Flat, parse(&["--shading", "flat"]).unwrap().shading);
+            let a = parse(&["--shading", "phong", "--specular", "phong"]).unwrap();
+            assert_eq!(Shading::Phong, a.shading);
+            assert_eq!(Reflection::Phong, a.reflection);
+        }
+    }
+
+    #[test]
+    fn errors() {
+        {
+            // 値がない
+            assert!(parse(&["--size"]).unwrap_err().contains("needs a value"));
+            assert!(parse(&["a.obj", "--light"]).unwrap_err().contains("needs a value"));
+            // 知らないオプション
+            assert!(parse(&["--foo"]).unwrap_err().contains("unknown option"));
+            // モデルは1つだけ
+            assert!(parse(&["a.obj", "b.obj"]).unwrap_err().contains("unexpected argument"));
+            // 知らない値
+            assert!(parse(&["--filter", "cubic"]).is_err());
+            assert!(parse(&["--wrap", "mirror"]).is_err());
+            assert!(parse(&["--fov", "180"]).is_err());
+            assert!(parse(&["--fit", "-0.5"]).is_err());
+            assert!(parse(&["--fit", "0"]).is_err());
+            assert!(parse(&["--fit", "1.5"]).is_err());
+            assert_eq!(1f64, parse(&["--fit", "1"]).unwrap().fit);
+            assert!(parse(&["--view", "back"]).is_err());
+            assert!(parse(&["--shading", "gouraud"]).is_err());
+            assert!(parse(&["--specular", "cook"]).is_err());
+        }
+    }
+
+    #[test]
+    fn size() {
+        {
+            assert_eq!(Ok((640, 480)), parse_size("640x480"));
+            assert!(parse_size("640").is_err());
+            assert!(parse_size("0x480").is_err());
+            assert!(parse_size("-640x480").is_err());
+            assert!(parse_size("ax480").is_err());
+            assert!(parse_size("640x480x3").is_err());
+        }
+    }
+
+    #[test]
+    fn point() {
+        {
+            assert_eq!(Ok(Point3::new(0f64, -1f64, -100f64, 1f64)), parse_point("0, -1,-100"));
+            assert!(parse_point("1,2").is_err());
+            assert!(parse_point("1,2,z").is_err());
+        }
+    }
+
+    #[test]
+    fn color() {
+        {
+            let rgb = |s: &str| parse_color(s).map(|c| (c.r, c.g, c.b));
+            assert_eq!(Ok((255, 128, 0)), rgb("#ff8000"));
+            assert_eq!(Ok((255, 128, 0)), rgb("#FF8000"));
+            assert_eq!(Ok((1, 2, 3)), rgb("1, 2 ,3"));
+            assert!(parse_color("#ff80").is_err());
+            assert!(parse_color("#gg0000").is_err());
+            assert!(parse_color("ff8000").is_err());
+            assert!(parse_color("256,0,0").is_err());
+            assert!(parse_color("1,2").is_err());
+        }
+    }
+
+    #[test]
+    fn light() {
+        {
+            let a = parse_light("ambient").unwrap();
+            assert_eq!(LightKind::Ambient, a.kind);
+            assert_eq!(0.1f64, a.intensity);
+            // 色と強さ
+            let d = parse_light("directional:0,-1,0:#ff0000:2").unwrap();
+            assert_eq!(2f64, d.intensity);
+            assert_eq!(LinearColor::new(1f64, 0f64, 0f64, 1f64), d.color);
+            // 色を省略して減衰だけ指定する
+            let p = parse_light("point:1,2,3::0.5:1,0,0.01").unwrap();
+            assert_eq!(LinearColor::new(1f64, 1f64, 1f64, 1f64), p.color);
+            assert_eq!(0.5f64, p.intensity);
+            assert_eq!(LightKind::Point {
+                position: Point3::new(1f64, 2f64, 3f64, 1f64),
+                attenuation: Attenuation::new(1f64, 0f64, 0.01f64)
+            }, p.kind);
+            // 内側の角度は省略するとOUTERの0.8倍
+            match parse_light("spot:0,10,0:0,0,0:30").unwrap().kind {
+                LightKind::Spot { inner, outer, .. } => {
+                    assert!((outer - 30f64.to_radians()).abs() < 1e-10f64);
+                    assert!((inner - 24f64.to_radians()).abs() < 1e-10f64);
+                }
+                k => panic!("{:?}", k)
+            }
+            match parse_light("spot:0,10,0:0,0,0:30,10").unwrap().kind {
+                LightKind::Spot { inner, .. } => assert!((inner - 10f64.to_radians()).abs() < 1e-10f64),
+                k => panic!("{:?}", k)
+            }
+            assert!(parse_light("sun").is_err());
+            assert!(parse_light("point").is_err());
+            assert!(parse_light("spot:0,10,0:0,0,0").is_err());
+            assert!(parse_light("spot:0,10,0:0,0,0:30,40").is_err());
+            // 環境光と平行光源は減衰しない
+            assert!(parse_light("ambient:#ffffff:1:1,0,0").is_err());
+            assert!(parse_light("directional:0,-1,0:#ffffff:1:1,0,0").is_err());
+            assert!(parse_light("point:0,0,0:#ffffff:1:1,0").is_err());
+            assert!(parse_light("point:0,0,0:#ffffff:1:0,0,0").is_err());
+            // --lightを繰り返すと全て使う
+            assert_eq!(2, parse(&["--light", "ambient", "--light", "point:0,0,0"]).unwrap().lights.len());
+        }
+    }
+}
@@ -0,0 +1,65 @@
+extern crate cairo;
+
+use std::time::{Duration, Instant};
+
+use util::world::World;
+use util::point3::Point3;
+use util::matrix4::Matrix4;
+use util::quaternion::Quaternion;
+
+// 経過時間をミリ秒で
+fn millis(d: Duration) -> f64 {
+    d.as_secs() as f64 * 1000.0f64 + f64::from(d.subsec_nanos()) / 1_000_000.0f64
+}
+
+// frames回繰り返して1回あたりのミリ秒を返す
+fn measure<F: FnMut(usize)>(frames: usize, mut f: F) -> f64 {
+    let start = Instant::now();
+    for i in 0..frames {
+        f(i);
+    }
+    millis(start.elapsed()) / frames as f64
+}
+
+// 頂点の変換と1フレームの描画にかかる時間を測る
+pub fn run(world: &World, width: i32, height: i32, frames: usize) -> Result<(), String> {
+    let frames = frames.max(1);
+    let vertices = &world.mesh.vertices;
+    println!("{} vertices, {} triangles, {} frames", vertices.len(), world.mesh.triangles.len(), frames);
+    let model = |i: usize| Quaternion::from_euler(0.0f64, (i % 360) as f64 / 180.0f64 * ::std::f64::consts::PI, 0.0f64)
+        .to_matrix();
+    let view = world.camera.view_matrix();
+    let projection = world.camera.projection_matrix();
+
+    // 回転、ビュー、投影を頂点ごとに順にかける
+    let mut sum = 0.0f64;
+    let separate = measure(frames, |i| {
+        let rotation = model(i);
+        let ps: Vec<Point3> = vertices.iter()
+            .map(|v| v.position.affin(&rotation).affin(&view).affin(&projection))
+            .collect();
+        sum += ps[0].w;
+    });
+    // 行列をフレームごとに1回まとめてから頂点ごとに1回だけかける
+    let combined = measure(frames, |i| {
+        let mvp: Matrix4 = &(&projection * &view) * &model(i);
+        let ps: Vec<Point3> = vertices.iter()
+            .map(|v| v.position.affin(&mvp))
+            .collect();
+        sum += ps[0].w;
+    });
+    println!("vertex transform (separate):  {:.3} ms/frame", separate);
+    println!("vertex transform (combined):  {:.3} ms/frame ({:.2}x)", combined, separate / combined);
+
+    // 描画全体
+    let surface = cairo::ImageSurface::create(cairo::Format::ARgb32, width, height)
+        .map_err(|e| format!("Failed to create image: {:?}", e))?;
+    let cr = cairo::Context::new(&surface);
+    let draw = measure(frames, |i| world.draw((i % 360) as i32, &cr));
+    println!("draw:                         {:.3} ms/frame", draw);
+    // 計算が最適化で消されないようにする
+    if sum.is_nan() {
+        println!("{}", sum);
+    }
+    Ok(())
+}
@@ -1,42 +1,95 @@
 mod util;
+mod args;
+mod bench;
 
 extern crate gio;
 extern crate gtk;
+extern crate gdk;
 extern crate cairo;
-#[macro_use] extern crate scan_rules;
+extern crate gdk_pixbuf;
 
-use gio::prelude::*;
 use gtk::prelude::*;
 use std::vec::Vec;
-use std::io::{BufReader, BufRead};
+use std::env;
 use std::fs::File;
+use std::process;
+use std::f64::consts::PI;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
+use std::time::Instant;
 use std::cell::{Cell, RefCell};
 
-use util::world::World;
-use util::point3::Point3;
-use util::polygon3::Polygon3;
+use util::world::{World, Shading};
+use util::mesh::Mesh;
 use util::color::Color;
-
-use cairo::enums::{FontSlant, FontWeight};
+use util::linear_color::LinearColor;
+use util::obj::ObjModel;
+use util::material::Material;
+use util::mtl::MtlLibrary;
+use util::texture::{Texture, Filter, Wrap};
+use util::load_error::LoadError;
+use util::fly::{Fly, Move};
+use args::{Args, Command};
 
 fn main() {
-    let filename = "miku.obj";
-//    let filename = "box.obj";
-    let polygons = obj_to_polygons(filename);
-    let width = 640.0f64;
-    let height = 480.0f64;
-    let scale = 50000.0f64;
-    let world = Rc::new(RefCell::new(World::new(&polygons, width, height, scale)));
+    let argv: Vec<String> = env::args().skip(1).collect();
+    let args = match Args::parse(&argv) {
+        Ok(args) => args,
+        Err(message) => {
+            eprintln!("{}", message);
+            eprintln!("{}", Args::usage());
+            process::exit(2);
+        }
+    };
+    if args.help {
+        println!("{}", Args::usage());
+        return;
+    }
+
+    // ヘッドレスで画像に出力(rust3d render model.obj -o out.png --size 1024x768 --yaw 220)
+    if args.command == Command::Render {
+        if let Err(message) = render(&args) {
+            eprintln!("{}", message);
+            process::exit(1);
+        }
+        return;
+    }
+
+    // 変換と描画の時間を測る(rust3d bench miku.obj --frames 100)
+    if args.command == Command::Bench {
+        let result = create_world(&args)
+            .map_err(|e| format!("Failed to load {}: {}", args.model, e))
+            .and_then(|world| bench::run(&world, args.width, args.height, args.frames));
+        if let Err(message) = result {
+            eprintln!("{}", message);
+            process::exit(1);
+        }
+        return;
+    }
 
     if gtk::init().is_err() {
         println!("Failed to initialize GTK.");
         return;
     }
 
+    let world = match create_world(&args) {
+        Ok(world) => Rc::new(RefCell::new(world)),
+        Err(e) => {
+            let message = format!("Failed to load {}: {}", args.model, e);
+            println!("{}", message);
+            let dialog = gtk::MessageDialog::new(None::<&gtk::Window>, gtk::DialogFlags::MODAL,
+                                                 gtk::MessageType::Error, gtk::ButtonsType::Ok, &message);
+            dialog.run();
+            dialog.destroy();
+            return;
+        }
+    };
+    let speed = args.speed;
+
     let window = gtk::Window::new(gtk::WindowType::Toplevel);
     window.set_title("rust3d");
-    window.set_default_size(640, 480);
+    window.set_default_size(args.width, args.height);
 
     let drawing_area = gtk::DrawingArea::new();
     window.add(&drawing_area);
@@ -46,24 +99,181 @@ fn main() {
         Inhibit(false)
     });
 
-    let rotate = Rc::new(Cell::new(220i32));
-    drawing_area.connect_draw(move |_: &gtk::DrawingArea, cr: &cairo::Context| {
+    let rotate = Rc::new(Cell::new(args.yaw));
+    // 自動で回転させるか(スペースキーで切り替え)
+    let spinning = Rc::new(Cell::new(true));
+    // 一人称視点で動かすか(Fキーで切り替え)
+    let flying = Rc::new(Cell::new(false));
+    // 最初はモデルの半径を1秒で進む
+    let fly = Rc::new(RefCell::new(Fly::new(world.borrow().mesh.radius().max(1.0f64))));
+    {
         let r = Rc::clone(&rotate);
         let w = Rc::clone(&world);
-        {
-            let _r = r.get();
-            let _w = w.borrow();
-            _w.draw(_r, cr);
-        }
-        {
-            let _r = r.get();
-            r.set((_r + 3) % 360);
-        }
-        Inhibit(false)
-    });
+        drawing_area.connect_draw(move |_: &gtk::DrawingArea, cr: &cairo::Context| {
+            w.borrow().draw(r.get(), cr);
+            Inhibit(false)
+        });
+    }
+    // ウィンドウの大きさに合わせる
+    {
+        let w = Rc::clone(&world);
+        drawing_area.connect_size_allocate(move |area, allocation| {
+            if allocation.width > 0 && allocation.height > 0 {
+                w.borrow_mut().resize(f64::from(allocation.width), f64::from(allocation.height));
+                area.queue_draw();
+            }
+        });
+    }
+
+    // マウス操作(左ドラッグで回転、中ドラッグで平行移動、ホイールで拡大縮小)
+    // 一人称視点では左ドラッグで向きを変え、ホイールで移動の速さを変える
+    drawing_area.add_events((gdk::EventMask::BUTTON_PRESS_MASK | gdk::EventMask::BUTTON_MOTION_MASK |
+        gdk::EventMask::SCROLL_MASK).bits() as i32);
+    let last = Rc::new(Cell::new((0.0f64, 0.0f64)));
+    {
+        let last = Rc::clone(&last);
+        drawing_area.connect_button_press_event(move |_, event| {
+            last.set(event.get_position());
+            Inhibit(false)
+        });
+    }
+    {
+        let w = Rc::clone(&world);
+        let flying = Rc::clone(&flying);
+        drawing_area.connect_motion_notify_event(move |area, event| {
+            let (x, y) = event.get_position();
+            let (x0, y0) = last.get();
+            last.set((x, y));
+            // 画面の高さを基準にした移動量
+            let height = f64::from(area.get_allocated_height().max(1));
+            let dx = (x - x0) / height;
+            let dy = (y - y0) / height;
+            let state = event.get_state();
+            if state.contains(gdk::ModifierType::BUTTON1_MASK) {
+                // 画面の高さ分ドラッグすると半周
+                if flying.get() {
+                    w.borrow_mut().camera.look(dx * PI, dy * PI);
+                } else {
+                    w.borrow_mut().camera.orbit(dx * PI, dy * PI);
+                }
+            } else if state.contains(gdk::ModifierType::BUTTON2_MASK) {
+                let mut world = w.borrow_mut();
+                world.camera.pan(dx, dy);
+                world.fit_clip_planes();
+            } else {
+                return Inhibit(false);
+            }
+            area.queue_draw();
+            Inhibit(true)
+        });
+    }
+    {
+        let w = Rc::clone(&world);
+        let flying = Rc::clone(&flying);
+        let fly = Rc::clone(&fly);
+        drawing_area.connect_scroll_event(move |area, event| {
+            let factor = match event.get_direction() {
+                gdk::ScrollDirection::Up => 0.9f64,
+                gdk::ScrollDirection::Down => 1.0f64 / 0.9f64,
+                _ => return Inhibit(false)
+            };
+            if flying.get() {
+                fly.borrow_mut().speed /= factor;
+                return Inhibit(true);
+            }
+            let mut world = w.borrow_mut();
+            world.camera.zoom(factor);
+            world.fit_clip_planes();
+            area.queue_draw();
+            Inhibit(true)
+        });
+    }
+    {
+        let s = Rc::clone(&spinning);
+        let flying = Rc::clone(&flying);
+        let fly = Rc::clone(&fly);
+        let w = Rc::clone(&world);
+        let area = drawing_area.clone();
+        window.connect_key_press_event(move |_, event| {
+            let key = event.get_keyval();
+            if key == gdk::enums::key::space {
+                s.set(!s.get());
+                return Inhibit(true);
+            }
+            // 陰影の付け方を切り替える
+            if key == gdk::enums::key::g || key == gdk::enums::key::G {
+                let mut world = w.borrow_mut();
+                world.shading = match world.shading {
+                    Shading::Flat => Shading::Gouraud,
+                    Shading::Gouraud => Shading::Phong,
+                    Shading::Phong => Shading::Flat
+                };
+                area.queue_draw();
+                return Inhibit(true);
+            }
+            if key == gdk::enums::key::f || key == gdk::enums::key::F {
+                flying.set(!flying.get());
+                fly.borrow_mut().clear();
+                // 一人称視点ではモデルを回さない
+                if flying.get() {
+                    s.set(false);
+                }
+                return Inhibit(true);
+            }
+            if !flying.get() {
+                return Inhibit(false);
+            }
+            match fly_move(key) {
+                Some(m) => fly.borrow_mut().press(m),
+                // +/-で移動の速さを変える
+                None if key == gdk::enums::key::plus || key == gdk::enums::key::equal =>
+                    fly.borrow_mut().speed *= 1.25f64,
+                None if key == gdk::enums::key::minus =>
+                    fly.borrow_mut().speed /= 1.25f64,
+                None => return Inhibit(false)
+            }
+            Inhibit(true)
+        });
+    }
+    {
+        let fly = Rc::clone(&fly);
+        window.connect_key_release_event(move |_, event| {
+            match fly_move(event.get_keyval()) {
+                Some(m) => {
+                    fly.borrow_mut().release(m);
+                    Inhibit(true)
+                }
+                None => Inhibit(false)
+            }
+        });
+    }
+    {
+        // 前回からの経過時間の分だけ動かす
+        let w = Rc::clone(&world);
+        let area = drawing_area.clone();
+        let last = Cell::new(Instant::now());
+        timeout_add(16, move || {
+            let now = Instant::now();
+            let elapsed = now.duration_since(last.get());
+            last.set(now);
+            let seconds = (elapsed.as_secs() as f64 + f64::from(elapsed.subsec_nanos()) * 1e-9f64).min(0.1f64);
+            let fly = fly.borrow();
+            if flying.get() && fly.is_moving() {
+                // 近づいても離れてもモデルが切り取られないように手前と奥の面を合わせ直す
+                let mut world = w.borrow_mut();
+                fly.update(&mut world.camera, seconds);
+                world.fit_clip_planes();
+                area.queue_draw();
+            }
+            gtk::Continue(true)
+        });
+    }
 
     timeout_add(100, move || {
-        drawing_area.queue_draw_area(0, 0, width as i32, height as i32);
+        if spinning.get() {
+            rotate.set((rotate.get() + speed).rem_euclid(360));
+            drawing_area.queue_draw();
+        }
         gtk::Continue(true)
     });
 
@@ -71,44 +281,132 @@ fn main() {
     gtk::main();
 }
 
-fn obj_to_polygons(filename: &str) -> Vec<(Polygon3, Color)> {
+// 一人称視点で移動するキー(WASDで前後左右、QEで上下)
+fn fly_move(key: gdk::enums::key::Key) -> Option<Move> {
+    use gdk::enums::key;
+    match key {
+        key::w | key::W => Some(Move::Forward),
+        key::s | key::S => Some(Move::Back),
+        key::a | key::A => Some(Move::Left),
+        key::d | key::D => Some(Move::Right),
+        key::e | key::E => Some(Move::Up),
+        key::q | key::Q => Some(Move::Down),
+        _ => None
+    }
+}
+
+// 引数に従ってモデルを読み込み、カメラなどを設定する
+fn create_world(args: &Args) -> Result<World, LoadError> {
+    let mesh = load_mesh(&args.model, args.texture.as_deref(), args.filter, args.wrap)?;
+    let mut world = World::new(mesh, f64::from(args.width), f64::from(args.height), args.fov.to_radians());
+    if let Some(view) = args.view {
+        world.camera.set_view(view);
+    }
+    if args.ortho {
+        world.camera.set_orthographic();
+    }
+    // モデルが画面に収まるようにカメラを置く
+    world.fit_to_view(args.fit);
+    if let Some(position) = args.position {
+        world.camera.position = position;
+    }
+    if let Some(look_at) = args.look_at {
+        world.camera.look_at = look_at;
+    }
+    // 指定したカメラの位置でもモデルが切り取られないようにする
+    if args.position.is_some() || args.look_at.is_some() {
+        world.fit_clip_planes();
+    }
+    world.shading = args.shading;
+    world.lighting.reflection = args.reflection;
+    if !args.lights.is_empty() {
+        world.lighting.lights = args.lights.clone();
+    }
+    world.background = args.background;
+    Ok(world)
+}
+
+// 画面を開かずに描画してPNGファイルに書き出す
+fn render(args: &Args) -> Result<(), String> {
+    let world = create_world(args).map_err(|e| format!("Failed to load {}: {}", args.model, e))?;
+    // 画像に描画
+    let surface = cairo::ImageSurface::create(cairo::Format::ARgb32, args.width, args.height)
+        .map_err(|e| format!("Failed to create image: {:?}", e))?;
+    {
+        let cr = cairo::Context::new(&surface);
+        world.draw(args.yaw.rem_euclid(360), &cr);
+    }
+    let mut file = File::create(&args.output).map_err(|e| format!("Failed to create {}: {}", args.output, e))?;
+    surface.write_to_png(&mut file).map_err(|e| format!("Failed to write {}: {:?}", args.output, e))?;
+    Ok(())
+}
+
+fn load_mesh(filename: &str, texture: Option<&str>, filter: Filter, wrap: Wrap) -> Result<Mesh, LoadError> {
     // 初期データ(objファイル)読み込み
-    let file = BufReader::new(File::open(filename).unwrap());
-    let mut polygons: Vec<Polygon3> = Vec::new();
-    let mut vs: Vec<Point3> = Vec::new();
-
-    for line_iter in file.lines() {
-        match line_iter  {
-            Ok(line) => {
-                // 頂点データ
-                scan!(&line; ("v ", let x: f64, let y: f64, let z: f64) => {
-                    let p = Point3::new(x, y, z, 1.0f64);
-//                    println!("{:?}", p);
-                    vs.push(p);
-                }).or_else(|n| {
-                    // ポリゴン(4点)
-                    scan!(&line; ("f ", let a: usize, "/", let _: usize, let b: usize, "/", let _: usize, let c: usize, "/", let _: usize, let d: usize, "/", let _: usize) => {
-                        let p1 = Polygon3::new(&vs[a - 1], &vs[b - 1], &vs[c - 1]);
-                        let p2 = Polygon3::new(&vs[a - 1], &vs[c - 1], &vs[d - 1]);
-//                        println!("{:?}", p1);
-//                        println!("{:?}", p2);
-                        polygons.push(p1);
-                        polygons.push(p2);
-                    })
-                }).or_else(|n| {
-                    // ポリゴン(3点)
-                    scan!(&line; ("f ", let a: usize, "/", let _: usize, let b: usize, "/", let _: usize, let c: usize, "/", let _: usize) => {
-                        let p = Polygon3::new(&vs[a - 1], &vs[b - 1], &vs[c - 1]);
-//                        println!("{:?}", p);
-                        polygons.push(p);
-                    })
-                });
-                // TODO: 中央に寄せる
+    let model = ObjModel::load(filename)?;
+    for skipped in model.skipped.iter() {
+        println!("{}:{}: {} ({})", filename, skipped.line, skipped.reason, skipped.text);
+    }
+    // マテリアル(objファイルからの相対パス)
+    let dir = Path::new(filename).parent().unwrap_or(Path::new(""));
+    let mut textures: HashMap<PathBuf, Rc<Texture>> = HashMap::new();
+    let mut load_texture = |path: PathBuf| -> Option<Rc<Texture>> {
+        if let Some(texture) = textures.get(&path) {
+            return Some(Rc::clone(texture));
+        }
+        match Texture::load(&path) {
+            Ok(mut texture) => {
+                texture.filter = filter;
+                texture.wrap = wrap;
+                let texture = Rc::new(texture);
+                textures.insert(path, Rc::clone(&texture));
+                Some(texture)
+            }
+            Err(e) => {
+                println!("Failed to load {}: {}", path.display(), e);
+                None
+            }
+        }
+    };
+    let mut libraries = Vec::new();
+    for name in model.material_libs.iter() {
+        let path = dir.join(name);
+        match MtlLibrary::load(&path) {
+            Ok(mut library) => {
+                for skipped in library.skipped.iter() {
+                    println!("{}:{}: {} ({})", path.display(), skipped.line, skipped.reason, skipped.text);
+                }
+                // テクスチャ(mtlファイルからの相対パス)
+                let mtl_dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+                for material in library.materials.iter_mut() {
+                    if let Some(ref map) = material.diffuse_map {
+                        material.texture = load_texture(mtl_dir.join(map));
+                    }
+                }
+                libraries.push(library);
             }
-            Err(e) => println!("{}", e)
+            Err(e) => println!("Failed to load {}: {}", path.display(), e)
+        }
+    }
+    for name in model.materials.iter() {
+        if libraries.iter().all(|l| l.find(name).is_none()) {
+            println!("{}: material '{}' not found", filename, name);
+        }
+    }
+    let texture = texture.and_then(|t| load_texture(PathBuf::from(t)));
+    // テクスチャにはKdを掛けるので、テクスチャがあれば既定の色は白にする
+    let color = if texture.is_some() { Color::new(255, 255, 255) } else { Color::new(255, 230, 230) };
+    let default = Material::new("default", &LinearColor::from_srgb(&color));
+    let mut mesh = Mesh::from_obj(&model, &libraries, &default);
+    // 中央に寄せる
+    mesh.center();
+    // 法線のない頂点は周りの面から求める
+    mesh.compute_normals();
+    // コマンドラインで指定したテクスチャは全てのマテリアルに使う
+    if let Some(texture) = texture {
+        for material in mesh.materials.iter_mut() {
+            material.texture = Some(Rc::clone(&texture));
         }
     }
-    polygons.into_iter()
-        .map(|p| (p, Color::new(255, 230, 230)))
-        .collect()
+    Ok(mesh)
 }
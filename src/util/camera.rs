@@ -1,26 +1,153 @@
 use util::point3::Point3;
 use util::vector3::Vector3;
 use util::matrix4::Matrix4;
+use util::quaternion::Quaternion;
+use util::transform::Transform;
 use util::polygon3::Polygon3;
-use util::screen::Screen;
+
+// 投影方法
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Projection {
+    // 透視投影(縦の視野角、ラジアン)
+    Perspective { fov: f64 },
+    // 平行投影(見える範囲の高さ、幅は高さ * aspect)
+    Orthographic { height: f64 }
+}
+
+// 決まった方向からの眺め
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum View {
+    // 正面(-Z側から)
+    Front,
+    // 右側面(+X側から)
+    Side,
+    // 上面(+Y側から)
+    Top,
+    // 等角(右斜め上の手前から)
+    Iso
+}
+
+impl View {
+    // 見る方向と上方向
+    pub fn direction(&self) -> (Vector3, Vector3) {
+        let y = Vector3::new(0.0f64, 1.0f64, 0.0f64, 0.0f64);
+        match *self {
+            View::Front => (Vector3::new(0.0f64, 0.0f64, 1.0f64, 0.0f64), y),
+            View::Side => (Vector3::new(-1.0f64, 0.0f64, 0.0f64, 0.0f64), y),
+            View::Top => (Vector3::new(0.0f64, -1.0f64, 0.0f64, 0.0f64), Vector3::new(0.0f64, 0.0f64, 1.0f64, 0.0f64)),
+            View::Iso => (Vector3::new(-1.0f64, -1.0f64, 1.0f64, 0.0f64).normalize(), y)
+        }
+    }
+}
 
 #[derive(Debug)]
 pub struct Camera {
     pub position: Point3,
     pub look_at: Point3,
     pub up: Vector3,
+    pub projection: Projection,
+    // 画面の幅 / 高さ
+    pub aspect: f64,
     pub near: f64,
     pub far: f64
 }
 
 impl Camera {
-    pub fn new(position: &Point3, look_at: &Point3, up: &Vector3, near: f64, far: f64) -> Camera {
-        Camera { position: *position, look_at: *look_at, up: *up, near, far }
+    pub fn new(position: &Point3, look_at: &Point3, up: &Vector3, projection: Projection, aspect: f64, near: f64, far: f64) -> Camera {
+        Camera { position: *position, look_at: *look_at, up: *up, projection, aspect, near, far }
     }
 
     // 裏を向いているか？
     pub fn is_cull(&self, polygon : Polygon3) -> bool {
-        polygon.normal().dot(&self.direction()) >= 0.0f64
+        let eye = match self.projection {
+            // カメラからポリゴンへの向き
+            Projection::Perspective { .. } => &Vector3::from(polygon.p1) - &self.position,
+            // 平行投影ではどこも同じ向き
+            Projection::Orthographic { .. } => self.direction()
+        };
+        polygon.normal().dot(&eye) >= 0.0f64
+    }
+
+    // 平行投影にする(look_atの位置で見える範囲は今のまま)
+    pub fn set_orthographic(&mut self) {
+        if let Projection::Perspective { .. } = self.projection {
+            self.projection = Projection::Orthographic { height: self.view_height() };
+        }
+    }
+
+    // look_atの位置で見える範囲の高さ
+    pub fn view_height(&self) -> f64 {
+        match self.projection {
+            Projection::Perspective { fov } => 2.0f64 * self.direction().norm() * (fov / 2.0f64).tan(),
+            Projection::Orthographic { height } => height
+        }
+    }
+
+    // look_atを中心にカメラを回す(yawは画面の上方向、pitchは右方向を軸にする)
+    pub fn orbit(&mut self, yaw: f64, pitch: f64) {
+        let (x, _, _) = self.axes();
+        let rotation = (&Quaternion::from_axis_angle(&self.up, yaw) *
+            &Quaternion::from_axis_angle(&x, pitch)).to_matrix();
+        let offset = &rotation * &(&Vector3::from(self.position) - &self.look_at);
+        self.position = &self.look_at + &offset;
+        self.up = (&rotation * &self.up).normalize();
+    }
+
+    // カメラの位置を変えずに向きを変える(yawは世界の上方向(+Y)、pitchは右方向を軸にする)
+    // 傾かないようにupは世界の上方向のままにし、真上と真下の手前で止める
+    pub fn look(&mut self, yaw: f64, pitch: f64) {
+        let limit = 89.0f64.to_radians();
+        let direction = self.direction();
+        let distance = direction.norm();
+        let d = direction.normalize();
+        // 水平の向きと上下の角度(pitchが正なら下を向く)
+        let heading = d.x.atan2(d.z) + yaw;
+        let elevation = (d.y.clamp(-1.0f64, 1.0f64).asin() - pitch).max(-limit).min(limit);
+        let direction = Vector3::new(
+            elevation.cos() * heading.sin() * distance,
+            elevation.sin() * distance,
+            elevation.cos() * heading.cos() * distance,
+            0.0f64);
+        self.look_at = &self.position + &direction;
+        self.up = Vector3::new(0.0f64, 1.0f64, 0.0f64, 0.0f64);
+    }
+
+    // カメラとlook_atを一緒に動かす(右、上、前方向の移動量)
+    pub fn translate(&mut self, right: f64, up: f64, forward: f64) {
+        let (x, y, z) = self.axes();
+        let delta = &(&(&x * right) + &(&y * up)) + &(&z * forward);
+        self.position = &self.position + &delta;
+        self.look_at = &self.look_at + &delta;
+    }
+
+    // カメラとlook_atを画面に平行に動かす(dx, dyはview_heightに対する割合、yは下向き)
+    pub fn pan(&mut self, dx: f64, dy: f64) {
+        let (x, y, _) = self.axes();
+        let height = self.view_height();
+        let delta = &(&y * (dy * height)) - &(&x * (dx * height));
+        self.position = &self.position + &delta;
+        self.look_at = &self.look_at + &delta;
+    }
+
+    // 近づく(factorが1より小さい)、離れる(1より大きい)
+    pub fn zoom(&mut self, factor: f64) {
+        match self.projection {
+            Projection::Perspective { .. } => {
+                let offset = &(&Vector3::from(self.position) - &self.look_at) * factor;
+                self.position = &self.look_at + &offset;
+                self.near *= factor;
+                self.far *= factor;
+            }
+            Projection::Orthographic { ref mut height } => *height *= factor
+        }
+    }
+
+    // viewの方向からlook_atを見る(距離は今のまま)
+    pub fn set_view(&mut self, view: View) {
+        let (direction, up) = view.direction();
+        let distance = self.direction().norm();
+        self.position = &self.look_at - &(&direction * distance);
+        self.up = up;
     }
 
     // 見ている方向
@@ -28,48 +155,234 @@ impl Camera {
         &Vector3::from(self.look_at) - &self.position
     }
 
-    // ビューポート変換
-    pub fn convert_to_view(&self, polygon: Polygon3) -> Polygon3 {
+    // 点から視点への向き(鏡面反射の計算用)
+    pub fn to_eye(&self, point: &Point3) -> Vector3 {
+        match self.projection {
+            Projection::Perspective { .. } => &Vector3::from(self.position) - point,
+            // 平行投影ではどこも同じ向き
+            Projection::Orthographic { .. } => &self.direction() * -1.0f64
+        }
+    }
+
+    // カメラから見た右、上、奥の単位ベクトル
+    pub fn axes(&self) -> (Vector3, Vector3, Vector3) {
         let z = self.direction().normalize();
         let x = (&self.up * &z).normalize();
         let y = (&z * &x).normalize();
-        let p = Vector3::from(self.position);
-        let tx = -1.0f64 * p.dot(&x);
-        let ty = -1.0f64 * p.dot(&y);
-        let tz = -1.0f64 * p.dot(&z);
-        let mat = Matrix4::new(
-          [x.x, x.y, x.z, tx,
-          y.x, y.y, y.z, ty,
-          z.x, z.y, z.z, tz,
-          0.0f64, 0.0f64, 0.0f64, 1.0f64]
-        );
-        polygon.affin(&mat)
-    }
-
-    // 投影変換
-    fn _projection(&self, point : &Point3, screen : &Screen) -> Point3 {
-      Point3::new(
-        self.near * 2.0f64 * point.x / screen.size.width,
-        self.near * 2.0f64 * point.y / screen.size.height,
-        (self.far + self.near) * point.z / (self.far - self.near) + (2.0f64 * self.near * self.far) / (self.far - self.near),
-        (2.0f64 * self.near * self.far) * point.w / (self.far - self.near)
-      )
-    }
-
-    // 投影変換
-    pub fn projection(&self, polygon : &Polygon3, screen : &Screen) -> Polygon3 {
-      Polygon3::new(
-        &self._projection(&(polygon.p1), screen),
-        &self._projection(&(polygon.p2), screen),
-        &self._projection(&(polygon.p3), screen)
-      )
-    }
-
-    pub fn perspective(&self, polygon : &Polygon3) -> Polygon3 {
-      Polygon3::new(
-          &(&(polygon.p1) / polygon.p1.w),
-          &(&(polygon.p2) / polygon.p2.w),
-          &(&(polygon.p3) / polygon.p3.w)
-      )
+        (x, y, z)
+    }
+
+    // ビューポート変換行列
+    pub fn view_matrix(&self) -> Matrix4 {
+        Matrix4::look_at(&self.position, &self.look_at, &self.up)
+    }
+
+    // modelからカメラの投影までの変換
+    pub fn transform(&self, model: &Matrix4) -> Transform {
+        Transform::new(model, &self.view_matrix(), &self.projection_matrix())
+    }
+
+    // 投影変換行列
+    pub fn projection_matrix(&self) -> Matrix4 {
+      match self.projection {
+        Projection::Perspective { fov } =>
+          Matrix4::perspective(fov, self.aspect, self.near, self.far),
+        Projection::Orthographic { height } =>
+          Matrix4::orthographic(height * self.aspect, height, self.near, self.far)
+      }
+    }
+
+    // 見える範囲を表す平面(投影変換後の同次座標で、ax + by + cz + dw >= 0が内側)
+    pub fn clip_planes(&self) -> [[f64; 4]; 6] {
+      [
+        // near
+        [0.0f64, 0.0f64, 1.0f64, 1.0f64],
+        // far
+        [0.0f64, 0.0f64, -1.0f64, 1.0f64],
+        // 左右
+        [1.0f64, 0.0f64, 0.0f64, 1.0f64],
+        [-1.0f64, 0.0f64, 0.0f64, 1.0f64],
+        // 上下
+        [0.0f64, 1.0f64, 0.0f64, 1.0f64],
+        [0.0f64, -1.0f64, 0.0f64, 1.0f64]
+      ]
+    }
+
+    // wで割る(見えるものはx, y, zが-1〜1の範囲に入る)
+    pub fn perspective_point(&self, point : &Point3) -> Point3 {
+      point / point.w
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::PI;
+
+    // (0, 0, -10)から原点を見る
+    fn camera() -> Camera {
+        Camera::new(
+            &Point3::new(0f64, 0f64, -10f64, 1f64),
+            &Point3::new(0f64, 0f64, 0f64, 1f64),
+            &Vector3::new(0f64, 1f64, 0f64, 0f64),
+            Projection::Perspective { fov: PI / 2f64 }, 2f64, 1f64, 100f64)
+    }
+
+    fn distance(c: &Camera) -> f64 {
+        c.direction().norm()
+    }
+
+    #[test]
+    fn view() {
+        {
+            // カメラが原点、look_atが+Z方向になる
+            let c = camera();
+            let m = c.view_matrix();
+            assert!(Point3::new(0f64, 0f64, 0f64, 1f64).approx_eq(&c.position.affin(&m), 1e-10f64));
+            assert!(Point3::new(0f64, 0f64, 10f64, 1f64).approx_eq(&c.look_at.affin(&m), 1e-10f64));
+        }
+    }
+
+    #[test]
+    fn projection() {
+        {
+            // nearでz = -1、視野角の端でy = 1、横はaspectの分だけ広い
+            let mut c = camera();
+            let p = c.perspective_point(&Point3::new(2f64, 1f64, 1f64, 1f64).affin(&c.projection_matrix()));
+            assert!(Point3::new(1f64, 1f64, -1f64, 1f64).approx_eq(&p, 1e-10f64), "{:?}", p);
+            // 平行投影では奥行きで大きさが変わらない
+            c.set_orthographic();
+            assert!((c.view_height() - 20f64).abs() < 1e-10f64);
+            let near = c.perspective_point(&Point3::new(20f64, 10f64, 1f64, 1f64).affin(&c.projection_matrix()));
+            let far = c.perspective_point(&Point3::new(20f64, 10f64, 100f64, 1f64).affin(&c.projection_matrix()));
+            assert!(Point3::new(1f64, 1f64, -1f64, 1f64).approx_eq(&near, 1e-10f64), "{:?}", near);
+            assert!(Point3::new(1f64, 1f64, 1f64, 1f64).approx_eq(&far, 1e-10f64), "{:?}", far);
+        }
+    }
+
+    #[test]
+    fn is_cull() {
+        {
+            let mut c = camera();
+            // カメラの方を向いている面は残し、裏返すと省く
+            let front = Polygon3::new(
+                &Point3::new(0f64, 0f64, 0f64, 1f64),
+                &Point3::new(0f64, 1f64, 0f64, 1f64),
+                &Point3::new(1f64, 0f64, 0f64, 1f64));
+            let back = Polygon3::new(&front.p1, &front.p3, &front.p2);
+            assert!(!c.is_cull(front));
+            assert!(c.is_cull(back));
+            // 横にある-X向きの面は透視投影では見え、平行投影では見えない
+            let side = Polygon3::new(
+                &Point3::new(5f64, 0f64, 0f64, 1f64),
+                &Point3::new(5f64, 0f64, 1f64, 1f64),
+                &Point3::new(5f64, 1f64, 0f64, 1f64));
+            assert!(!c.is_cull(side));
+            c.set_orthographic();
+            assert!(c.is_cull(side));
+            assert!(!c.is_cull(front));
+        }
+    }
+
+    #[test]
+    fn to_eye() {
+        {
+            // 透視投影では点ごとに向きが変わり、平行投影では見ている方向の逆
+            let mut c = camera();
+            let p = Point3::new(5f64, 0f64, 0f64, 1f64);
+            assert!(Vector3::new(-5f64, 0f64, -10f64, 1f64).approx_eq(&c.to_eye(&p), 1e-10f64));
+            c.set_orthographic();
+            assert!(Vector3::new(0f64, 0f64, -10f64, 1f64).approx_eq(&c.to_eye(&p), 1e-10f64));
+        }
+    }
+
+    #[test]
+    fn orbit() {
+        {
+            // look_atからの距離は変わらず、半周すると反対側
+            let mut c = camera();
+            c.orbit(PI / 3f64, 0.4f64);
+            assert!((distance(&c) - 10f64).abs() < 1e-10f64);
+            let mut c = camera();
+            c.orbit(PI, 0f64);
+            assert!(Point3::new(0f64, 0f64, 10f64, 1f64).approx_eq(&c.position, 1e-10f64), "{:?}", c.position);
+            // 上を通っても上方向は保たれる(ジンバルロックしない)
+            let mut c = camera();
+            c.orbit(0f64, PI / 2f64);
+            assert!(Point3::new(0f64, 10f64, 0f64, 1f64).approx_eq(&c.position, 1e-10f64), "{:?}", c.position);
+            let (_, y, _) = c.axes();
+            assert!((y.z - 1f64).abs() < 1e-10f64, "{:?}", y);
+        }
+    }
+
+    #[test]
+    fn pan_zoom() {
+        {
+            // 平行移動はカメラとlook_atを一緒に動かす
+            let mut c = camera();
+            let height = c.view_height();
+            c.pan(0.5f64, 0f64);
+            assert!((c.position.x - -0.5f64 * height).abs() < 1e-10f64);
+            assert!((c.look_at.x - -0.5f64 * height).abs() < 1e-10f64);
+            // 拡大は距離を変え、平行投影では見える範囲を変える
+            let mut c = camera();
+            c.zoom(0.5f64);
+            assert!((distance(&c) - 5f64).abs() < 1e-10f64);
+            c.set_orthographic();
+            let height = c.view_height();
+            c.zoom(2f64);
+            assert!((c.view_height() - height * 2f64).abs() < 1e-10f64);
+        }
+    }
+
+    #[test]
+    fn look_translate() {
+        {
+            // 向きを変えてもカメラの位置は同じ
+            let mut c = camera();
+            c.look(PI / 2f64, 0f64);
+            assert!(Point3::new(0f64, 0f64, -10f64, 1f64).approx_eq(&c.position, 1e-10f64));
+            assert!(Point3::new(10f64, 0f64, -10f64, 1f64).approx_eq(&c.look_at, 1e-10f64), "{:?}", c.look_at);
+            // 前に進むと向いている方向に動く
+            c.translate(0f64, 0f64, 2f64);
+            assert!(Point3::new(2f64, 0f64, -10f64, 1f64).approx_eq(&c.position, 1e-10f64), "{:?}", c.position);
+        }
+    }
+
+    #[test]
+    fn look_level() {
+        {
+            // 上下を向いたあとに左右を向いても傾かない
+            let mut c = camera();
+            c.look(0f64, -0.5f64);
+            for _ in 0..20 {
+                c.look(0.3f64, 0f64);
+                let (x, _, _) = c.axes();
+                assert!(x.y.abs() < 1e-10f64, "{:?}", x);
+                assert!(Vector3::new(0f64, 1f64, 0f64, 0f64).approx_eq(&c.up, 1e-10f64));
+            }
+            // 上下の角度は変わらない
+            let d = c.direction().normalize();
+            assert!((d.y.asin() - 0.5f64).abs() < 1e-10f64, "{:?}", d);
+            // 真上を越えて回らない
+            c.look(0f64, -PI);
+            let d = c.direction().normalize();
+            assert!((d.y.asin() - 89f64.to_radians()).abs() < 1e-10f64, "{:?}", d);
+            assert!((distance(&c) - 10f64).abs() < 1e-10f64);
+        }
+    }
+
+    #[test]
+    fn set_view() {
+        {
+            // 上から見ると奥(+Z)が画面の上になる
+            let mut c = camera();
+            c.set_view(View::Top);
+            assert!(Point3::new(0f64, 10f64, 0f64, 1f64).approx_eq(&c.position, 1e-10f64));
+            let p = Point3::new(0f64, 0f64, 1f64, 1f64).affin(&c.view_matrix());
+            assert!(p.y > 0f64);
+            c.set_view(View::Iso);
+            assert!((distance(&c) - 10f64).abs() < 1e-10f64);
+        }
     }
 }
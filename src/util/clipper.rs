@@ -0,0 +1,136 @@
+use util::point3::Point3;
+
+// 切り取り後の頂点
+// barycentricは元の三角形の頂点に対する重み
+#[derive(Debug, Copy, Clone)]
+pub struct ClipVertex {
+    pub position: Point3,
+    pub barycentric: [f64; 3]
+}
+
+impl ClipVertex {
+    pub fn new(position: &Point3, barycentric: [f64; 3]) -> ClipVertex {
+        ClipVertex { position: *position, barycentric }
+    }
+
+    // selfとotherの間をtで線形補間
+    fn lerp(&self, other: &ClipVertex, t: f64) -> ClipVertex {
+        let p = &self.position;
+        let q = &other.position;
+        let b = &self.barycentric;
+        let c = &other.barycentric;
+        ClipVertex::new(
+            &Point3::new(p.x + (q.x - p.x) * t, p.y + (q.y - p.y) * t, p.z + (q.z - p.z) * t, p.w + (q.w - p.w) * t),
+            [b[0] + (c[0] - b[0]) * t, b[1] + (c[1] - b[1]) * t, b[2] + (c[2] - b[2]) * t])
+    }
+}
+
+// 平面までの符号付き距離(正なら内側)
+// 平面は同次座標で(a, b, c, d)、ax + by + cz + dw >= 0が内側
+fn distance(p: &Point3, plane: &[f64; 4]) -> f64 {
+    plane[0] * p.x + plane[1] * p.y + plane[2] * p.z + plane[3] * p.w
+}
+
+// 全ての平面の内側か
+pub fn is_inside(p: &Point3, planes: &[[f64; 4]]) -> bool {
+    planes.iter().all(|plane| distance(p, plane) >= 0.0f64)
+}
+
+// 多角形を1つの平面で切り取る(Sutherland-Hodgman)
+fn clip_against(polygon: &[ClipVertex], plane: &[f64; 4]) -> Vec<ClipVertex> {
+    let mut result = Vec::with_capacity(polygon.len() + 1);
+    for i in 0..polygon.len() {
+        let current = &polygon[i];
+        let next = &polygon[(i + 1) % polygon.len()];
+        let d1 = distance(&current.position, plane);
+        let d2 = distance(&next.position, plane);
+        if d1 >= 0.0f64 {
+            result.push(*current);
+        }
+        // 辺が平面をまたぐなら交点を加える
+        if (d1 >= 0.0f64) != (d2 >= 0.0f64) {
+            result.push(current.lerp(next, d1 / (d1 - d2)));
+        }
+    }
+    result
+}
+
+// 射影後(wで割る前)の三角形を平面群で切り取り、見える部分を三角形に分けて返す
+pub fn clip_triangle(vertices: &[Point3; 3], planes: &[[f64; 4]]) -> Vec<[ClipVertex; 3]> {
+    let triangle = [
+        ClipVertex::new(&vertices[0], [1.0f64, 0.0f64, 0.0f64]),
+        ClipVertex::new(&vertices[1], [0.0f64, 1.0f64, 0.0f64]),
+        ClipVertex::new(&vertices[2], [0.0f64, 0.0f64, 1.0f64])];
+    // 全て内側ならそのまま
+    if vertices.iter().all(|v| is_inside(v, planes)) {
+        return vec![triangle];
+    }
+    let mut polygon = triangle.to_vec();
+    for plane in planes.iter() {
+        polygon = clip_against(&polygon, plane);
+        if polygon.len() < 3 {
+            return Vec::new();
+        }
+    }
+    // 凸多角形なので扇形に分割
+    (1..(polygon.len() - 1))
+        .map(|i| [polygon[0], polygon[i], polygon[i + 1]])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 手前(z >= -w)
+    const NEAR: [f64; 4] = [0.0f64, 0.0f64, 1.0f64, 1.0f64];
+
+    #[test]
+    fn inside() {
+        {
+            let vs = [
+                Point3::new(0.0f64, 0.0f64, 0.0f64, 1.0f64),
+                Point3::new(1.0f64, 0.0f64, 0.0f64, 1.0f64),
+                Point3::new(0.0f64, 1.0f64, 0.0f64, 1.0f64)];
+            assert!(vs.iter().all(|v| is_inside(v, &[NEAR])));
+            let ts = clip_triangle(&vs, &[NEAR]);
+            assert_eq!(1, ts.len());
+            assert_eq!([0.0f64, 1.0f64, 0.0f64], ts[0][1].barycentric);
+        }
+    }
+
+    #[test]
+    fn outside() {
+        {
+            let vs = [
+                Point3::new(0.0f64, 0.0f64, -2.0f64, 1.0f64),
+                Point3::new(1.0f64, 0.0f64, -2.0f64, 1.0f64),
+                Point3::new(0.0f64, 1.0f64, -3.0f64, 1.0f64)];
+            assert!(!is_inside(&vs[0], &[NEAR]));
+            assert_eq!(0, clip_triangle(&vs, &[NEAR]).len());
+        }
+    }
+
+    #[test]
+    fn split() {
+        {
+            // 1点だけ手前にはみ出すと四角形になり、2つの三角形に分かれる
+            let vs = [
+                Point3::new(0.0f64, 0.0f64, -3.0f64, 1.0f64),
+                Point3::new(1.0f64, 0.0f64, 1.0f64, 1.0f64),
+                Point3::new(0.0f64, 1.0f64, 1.0f64, 1.0f64)];
+            let ts = clip_triangle(&vs, &[NEAR]);
+            assert_eq!(2, ts.len());
+            for t in ts.iter() {
+                for v in t.iter() {
+                    assert!(v.position.z >= -1.0f64 - 1e-10f64);
+                    // 重心座標から元の位置が復元できる
+                    let b = &v.barycentric;
+                    assert!((b[0] + b[1] + b[2] - 1.0f64).abs() < 1e-10f64);
+                    let z = vs[0].z * b[0] + vs[1].z * b[1] + vs[2].z * b[2];
+                    assert!((z - v.position.z).abs() < 1e-10f64);
+                }
+            }
+        }
+    }
+}
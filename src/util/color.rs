@@ -1,12 +1,39 @@
-#[derive(Debug, Copy, Clone)]
+// 8bitの色(aは不透明度で255が不透明)
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Color {
     pub r: u8,
     pub g: u8,
-    pub b: u8
+    pub b: u8,
+    pub a: u8
 }
 
 impl Color {
+    // 不透明な色
     pub fn new(r: u8, g: u8, b: u8) -> Color {
-        Color { r: r, g: g, b: b }
+        Color::rgba(r, g, b, 255)
     }
-}
\ No newline at end of file
+    pub fn rgba(r: u8, g: u8, b: u8, a: u8) -> Color {
+        Color { r, g, b, a }
+    }
+    // 各要素の差がtolerance以下か(丸めの誤差を許す)
+    pub fn approx_eq(&self, rhs: &Color, tolerance: u8) -> bool {
+        let near = |a: u8, b: u8| (i16::from(a) - i16::from(b)).abs() <= i16::from(tolerance);
+        near(self.r, rhs.r) && near(self.g, rhs.g) && near(self.b, rhs.b) && near(self.a, rhs.a)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn approx_eq() {
+        {
+            let a = Color::new(10, 20, 30);
+            assert_eq!(Color::new(10, 20, 30), a);
+            assert!(a.approx_eq(&Color::new(11, 19, 30), 1));
+            assert!(!a.approx_eq(&Color::new(12, 20, 30), 1));
+            assert!(!a.approx_eq(&Color::rgba(10, 20, 30, 128), 1));
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use util::camera::Camera;
+
+// 移動の方向
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Move {
+    Forward,
+    Back,
+    Left,
+    Right,
+    Up,
+    Down
+}
+
+// 一人称視点でカメラを動かす(押されているキーの状態を持つ)
+#[derive(Debug)]
+pub struct Fly {
+    // 1秒あたりの移動量
+    pub speed: f64,
+    moves: Vec<Move>
+}
+
+impl Fly {
+    pub fn new(speed: f64) -> Fly {
+        Fly { speed, moves: Vec::new() }
+    }
+
+    // キーが押された
+    pub fn press(&mut self, m: Move) {
+        if !self.moves.contains(&m) {
+            self.moves.push(m);
+        }
+    }
+
+    // キーが離された
+    pub fn release(&mut self, m: Move) {
+        self.moves.retain(|&x| x != m);
+    }
+
+    // 全てのキーを離す
+    pub fn clear(&mut self) {
+        self.moves.clear();
+    }
+
+    pub fn is_moving(&self) -> bool {
+        !self.moves.is_empty()
+    }
+
+    // 経過時間(秒)の分だけカメラを動かす(フレームレートによらず同じ速さになる)
+    pub fn update(&self, camera: &mut Camera, seconds: f64) {
+        let axis = |plus: Move, minus: Move|
+            (if self.moves.contains(&plus) { 1.0f64 } else { 0.0f64 }) -
+            (if self.moves.contains(&minus) { 1.0f64 } else { 0.0f64 });
+        let right = axis(Move::Right, Move::Left);
+        let up = axis(Move::Up, Move::Down);
+        let forward = axis(Move::Forward, Move::Back);
+        // 斜めに動くときも速さは同じにする
+        let length = (right * right + up * up + forward * forward).sqrt();
+        if length > 0.0f64 {
+            let distance = self.speed * seconds / length;
+            camera.translate(right * distance, up * distance, forward * distance);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use util::point3::Point3;
+    use util::vector3::Vector3;
+    use util::camera::Projection;
+
+    fn camera() -> Camera {
+        Camera::new(
+            &Point3::new(0.0f64, 0.0f64, -10.0f64, 1.0f64),
+            &Point3::new(0.0f64, 0.0f64, 0.0f64, 1.0f64),
+            &Vector3::new(0.0f64, 1.0f64, 0.0f64, 0.0f64),
+            Projection::Perspective { fov: 1.0f64 }, 1.0f64, 1.0f64, 100.0f64)
+    }
+
+    #[test]
+    fn update() {
+        {
+            // 速さ * 時間だけ前に進み、look_atも一緒に動く
+            let mut c = camera();
+            let mut f = Fly::new(4.0f64);
+            f.press(Move::Forward);
+            f.update(&mut c, 0.5f64);
+            assert!((c.position.z - -8.0f64).abs() < 1e-10f64);
+            assert!((c.look_at.z - 2.0f64).abs() < 1e-10f64);
+            // 前と後ろを同時に押すと動かない
+            f.press(Move::Back);
+            f.update(&mut c, 0.5f64);
+            assert!((c.position.z - -8.0f64).abs() < 1e-10f64);
+            // 斜めでも速さは同じ
+            f.clear();
+            f.press(Move::Right);
+            f.press(Move::Up);
+            f.update(&mut c, 1.0f64);
+            assert!((c.position.x * c.position.x + c.position.y * c.position.y - 16.0f64).abs() < 1e-10f64);
+            assert!(c.position.x > 0.0f64 && c.position.y > 0.0f64);
+        }
+    }
+}
@@ -0,0 +1,118 @@
+extern crate cairo;
+
+use util::color::Color;
+use util::linear_color::LinearColor;
+
+// 画素単位で描画するための画像と深度(Zバッファ)
+#[derive(Debug, Clone)]
+pub struct FrameBuffer {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<Color>,
+    pub depth: Vec<f64>
+}
+
+impl FrameBuffer {
+    pub fn new(width: usize, height: usize) -> FrameBuffer {
+        FrameBuffer {
+            width,
+            height,
+            pixels: vec![Color::new(0, 0, 0); width * height],
+            depth: vec![f64::INFINITY; width * height]
+        }
+    }
+
+    // 全体を塗りつぶし、深度を無限遠に戻す
+    pub fn clear(&mut self, color: &Color) {
+        for p in self.pixels.iter_mut() {
+            *p = *color;
+        }
+        for d in self.depth.iter_mut() {
+            *d = f64::INFINITY;
+        }
+    }
+
+    // 深度テスト(手前なら深度を更新してtrue)
+    pub fn test_and_set_depth(&mut self, x: usize, y: usize, z: f64) -> bool {
+        let i = y * self.width + x;
+        if z < self.depth[i] {
+            self.depth[i] = z;
+            true
+        } else {
+            false
+        }
+    }
+
+    // 深度テストだけ(半透明の面は奥の面を隠さないので深度を更新しない)
+    pub fn test_depth(&self, x: usize, y: usize, z: f64) -> bool {
+        z < self.depth[y * self.width + x]
+    }
+
+    #[cfg(test)]
+    pub fn get(&self, x: usize, y: usize) -> Color {
+        self.pixels[y * self.width + x]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, color: &Color) {
+        self.pixels[y * self.width + x] = *color;
+    }
+
+    // 不透明度に従って今の色の上に重ねる(線形な値で混ぜる)
+    pub fn blend(&mut self, x: usize, y: usize, color: &Color) {
+        let i = y * self.width + x;
+        let dst = LinearColor::from_srgb(&self.pixels[i]);
+        self.pixels[i] = LinearColor::from_srgb(color).over(&dst).to_srgb();
+    }
+
+    // ARGB32の画素データ(ネイティブエンディアンの32bit値で、色は不透明度を掛けておく)
+    fn to_argb(&self) -> Vec<u8> {
+        let mut data: Vec<u8> = Vec::with_capacity(self.width * self.height * 4);
+        for c in self.pixels.iter() {
+            let a = u32::from(c.a);
+            let premultiply = |x: u8| (u32::from(x) * a + 127) / 255;
+            let argb = a << 24 | premultiply(c.r) << 16 | premultiply(c.g) << 8 | premultiply(c.b);
+            data.extend_from_slice(&argb.to_ne_bytes());
+        }
+        data
+    }
+
+    // cairoの画像に変換
+    pub fn to_surface(&self) -> cairo::ImageSurface {
+        let stride = self.width * 4;
+        let data = self.to_argb();
+        cairo::ImageSurface::create_for_data(data.into_boxed_slice(), |_| (), cairo::Format::ARgb32,
+                                             self.width as i32, self.height as i32, stride as i32)
+            .expect("failed to create image surface")
+    }
+
+    // cairoのコンテキストの(x, y)に転送
+    pub fn draw(&self, cr: &cairo::Context, x: f64, y: f64) {
+        if self.width == 0 || self.height == 0 {
+            return;
+        }
+        let surface = self.to_surface();
+        cr.save();
+        cr.set_source_surface(&surface, x, y);
+        cr.paint();
+        cr.restore();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_argb() {
+        {
+            let mut fb = FrameBuffer::new(2, 1);
+            fb.set(0, 0, &Color::new(255, 128, 0));
+            fb.set(1, 0, &Color::rgba(255, 128, 0, 128));
+            let data = fb.to_argb();
+            let pixel = |i: usize| u32::from_ne_bytes([data[i * 4], data[i * 4 + 1], data[i * 4 + 2], data[i * 4 + 3]]);
+            assert_eq!(0xffff_8000u32, pixel(0));
+            // 半透明なら色に不透明度を掛ける
+            assert_eq!(0x8080_4000u32, pixel(1));
+        }
+    }
+}
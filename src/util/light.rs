@@ -1,28 +1,370 @@
 use util::point3::Point3;
-use util::polygon3::Polygon3;
 use util::vector3::Vector3;
-use util::color::Color;
+use util::linear_color::LinearColor;
+use util::material::Material;
+
+// 鏡面反射の計算方法
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Reflection {
+    // 光の反射ベクトルと視線のなす角(Phong)
+    Phong,
+    // 光源と視線の中間ベクトルと法線のなす角(Blinn-Phong)
+    BlinnPhong
+}
+
+// 距離による減衰(1 / (constant + linear * d + quadratic * d * d))
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Attenuation {
+    pub constant: f64,
+    pub linear: f64,
+    pub quadratic: f64
+}
+
+impl Attenuation {
+    pub fn new(constant: f64, linear: f64, quadratic: f64) -> Attenuation {
+        Attenuation { constant, linear, quadratic }
+    }
+    // 減衰しない
+    pub fn none() -> Attenuation {
+        Attenuation::new(1.0f64, 0.0f64, 0.0f64)
+    }
+    // 距離dでの光の割合
+    pub fn factor(&self, d: f64) -> f64 {
+        1.0f64 / (self.constant + self.linear * d + self.quadratic * d * d).max(1e-10f64)
+    }
+}
+
+// 光源の種類
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum LightKind {
+    // どの面も同じだけ照らす
+    Ambient,
+    // 無限遠から平行に照らす(directionは光の進む向き)
+    Directional { direction: Vector3 },
+    // 位置から全方向に照らす
+    Point { position: Point3, attenuation: Attenuation },
+    // 位置からdirectionの向きに円錐状に照らす
+    // innerまでは同じ明るさで、outer(どちらも中心からの角度)に向かって暗くなる
+    Spot { position: Point3, direction: Vector3, inner: f64, outer: f64, attenuation: Attenuation }
+}
 
 #[derive(Debug, Copy, Clone)]
 pub struct Light {
-    pub position: Point3
+    pub kind: LightKind,
+    // 線形な値
+    pub color: LinearColor,
+    pub intensity: f64
 }
 
 impl Light {
-    pub fn new(position: &Point3) -> Light {
-        Light { position: *position }
-    }
-    // 拡散光の計算(ランバードの余弦則)
-    pub fn get_diffuse_color(&self, color : &Color, polygon3: &Polygon3) -> Color {
-        let l = Vector3::from(&self.position - &polygon3.p1).normalize();
-        let cosa = l.dot(&polygon3.normal().normalize());
-        let level = if cosa >= 0.0f64 { cosa * 0.9f64 } else { 0.0f64 };
-        let mut r = color.r as f64 * level + 0.1f64;
-        let mut g = color.g as f64 * level + 0.1f64;
-        let mut b = color.b as f64 * level + 0.1f64;
-        r = 255.0f64.min((255.0f64 * 0.1f64).max(r));
-        g = 255.0f64.min((255.0f64 * 0.1f64).max(g));
-        b = 255.0f64.min((255.0f64 * 0.1f64).max(b));
-        Color::new(r as u8, g as u8, b as u8)
+    pub fn new(kind: LightKind, color: &LinearColor, intensity: f64) -> Light {
+        Light { kind, color: *color, intensity }
+    }
+    pub fn ambient(color: &LinearColor, intensity: f64) -> Light {
+        Light::new(LightKind::Ambient, color, intensity)
+    }
+    pub fn directional(direction: &Vector3, color: &LinearColor, intensity: f64) -> Light {
+        Light::new(LightKind::Directional { direction: *direction }, color, intensity)
+    }
+    pub fn point(position: &Point3, attenuation: &Attenuation, color: &LinearColor, intensity: f64) -> Light {
+        Light::new(LightKind::Point { position: *position, attenuation: *attenuation }, color, intensity)
+    }
+    pub fn spot(position: &Point3, direction: &Vector3, inner: f64, outer: f64, attenuation: &Attenuation,
+                color: &LinearColor, intensity: f64) -> Light {
+        Light::new(LightKind::Spot {
+            position: *position, direction: *direction, inner, outer, attenuation: *attenuation }, color, intensity)
+    }
+    // 位置から光源への向きと、そこに届く光の割合(環境光はNone)
+    fn incident(&self, position: &Point3) -> Option<(Vector3, f64)> {
+        match self.kind {
+            LightKind::Ambient => None,
+            LightKind::Directional { direction } =>
+                Some((&direction.normalize() * -1.0f64, self.intensity)),
+            LightKind::Point { position: p, attenuation } => {
+                let l = Vector3::from(&p - position);
+                Some((l.normalize(), self.intensity * attenuation.factor(l.norm())))
+            }
+            LightKind::Spot { position: p, direction, inner, outer, attenuation } => {
+                let l = Vector3::from(&p - position);
+                let to_light = l.normalize();
+                // 円錐の中心からの角度で弱める
+                let cosa = -to_light.dot(&direction.normalize());
+                let (cos_inner, cos_outer) = (inner.cos(), outer.cos());
+                let cone = if cosa >= cos_inner {
+                    1.0f64
+                } else if cosa <= cos_outer {
+                    0.0f64
+                } else {
+                    let t = (cosa - cos_outer) / (cos_inner - cos_outer);
+                    t * t * (3.0f64 - 2.0f64 * t)
+                };
+                Some((to_light, self.intensity * attenuation.factor(l.norm()) * cone))
+            }
+        }
+    }
+    // 拡散光の強さ(ランバードの余弦則)
+    pub fn get_diffuse_level(&self, position: &Point3, normal: &Vector3) -> f64 {
+        match self.incident(position) {
+            None => self.intensity,
+            Some((l, strength)) => {
+                let cosa = l.dot(&normal.normalize());
+                if cosa >= 0.0f64 { cosa * strength } else { 0.0f64 }
+            }
+        }
+    }
+    // 鏡面反射の強さ(eyeは位置から視点への向き)
+    pub fn get_specular_level(&self, position: &Point3, normal: &Vector3, eye: &Vector3,
+                              shininess: f64, reflection: Reflection) -> f64 {
+        if shininess <= 0.0f64 {
+            return 0.0f64;
+        }
+        let (l, strength) = match self.incident(position) {
+            Some(incident) => incident,
+            None => return 0.0f64
+        };
+        let n = normal.normalize();
+        let cosa = l.dot(&n);
+        // 光の当たらない面は光らない
+        if cosa <= 0.0f64 {
+            return 0.0f64;
+        }
+        let v = eye.normalize();
+        let cosb = match reflection {
+            Reflection::Phong => (&(&n * (2.0f64 * cosa)) - &l).dot(&v),
+            Reflection::BlinnPhong => (&l + &v).normalize().dot(&n)
+        };
+        if cosb > 0.0f64 { cosb.powf(shininess) * strength } else { 0.0f64 }
+    }
+    // 光の色を掛けたRGBごとの強さ
+    fn rgb(&self, level: f64) -> LinearColor {
+        &self.color * level
+    }
+}
+
+// シーン全体の光源
+#[derive(Debug, Clone)]
+pub struct Lighting {
+    pub lights: Vec<Light>,
+    pub reflection: Reflection
+}
+
+impl Lighting {
+    pub fn new(lights: Vec<Light>) -> Lighting {
+        Lighting { lights, reflection: Reflection::BlinnPhong }
+    }
+    // 環境光の強さをRGBごとに足す
+    pub fn get_ambient_level(&self) -> LinearColor {
+        let none = LinearColor::new(0.0f64, 0.0f64, 0.0f64, 0.0f64);
+        self.lights.iter().filter(|l| l.kind == LightKind::Ambient)
+            .fold(none, |ambient, light| &ambient + &light.rgb(light.intensity))
+    }
+    // 環境光以外の光源の拡散光と鏡面反射の強さをRGBごとに足す
+    pub fn get_levels(&self, position: &Point3, normal: &Vector3, eye: &Vector3, shininess: f64) -> (LinearColor, LinearColor) {
+        let none = LinearColor::new(0.0f64, 0.0f64, 0.0f64, 0.0f64);
+        self.lights.iter().filter(|l| l.kind != LightKind::Ambient).fold((none, none), |(diffuse, specular), light| (
+            &diffuse + &light.rgb(light.get_diffuse_level(position, normal)),
+            &specular + &light.rgb(light.get_specular_level(position, normal, eye, shininess, self.reflection))))
+    }
+    // 環境光、拡散光、鏡面反射をまとめて計算
+    pub fn shade(&self, color: &LinearColor, material: &Material, position: &Point3, normal: &Vector3,
+                 eye: &Vector3) -> LinearColor {
+        let (diffuse, specular) = self.get_levels(position, normal, eye, material.shininess);
+        self.apply(color, material, &diffuse, &specular)
+    }
+    // 光の強さを色に反映(鏡面反射の色は材質の色と関係なく足す)
+    // 環境光はKa(なければcolor)に掛け、不透明度はcolorのものにdを掛ける
+    pub fn apply(&self, color: &LinearColor, material: &Material, level: &LinearColor,
+                 specular_level: &LinearColor) -> LinearColor {
+        let ambient = &material.ambient_color(color) * &self.get_ambient_level();
+        let c = &(&ambient + &(color * level)) + &(&material.specular_color() * specular_level);
+        LinearColor::new(c.r, c.g, c.b, color.a * material.dissolve).clamp()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use util::mtl::MtlLibrary;
+
+    fn point(x: f64, y: f64, z: f64) -> Point3 {
+        Point3::new(x, y, z, 1f64)
+    }
+
+    fn vector(x: f64, y: f64, z: f64) -> Vector3 {
+        Vector3::new(x, y, z, 0f64)
+    }
+
+    fn rgb(r: f64, g: f64, b: f64) -> LinearColor {
+        LinearColor::new(r, g, b, 1f64)
+    }
+
+    fn white() -> LinearColor {
+        rgb(1f64, 1f64, 1f64)
+    }
+
+    #[test]
+    fn diffuse() {
+        {
+            let light = Light::point(&point(0f64, 10f64, 0f64), &Attenuation::none(), &white(), 0.9f64);
+            let up = vector(0f64, 1f64, 0f64);
+            assert!((light.get_diffuse_level(&point(0f64, 0f64, 0f64), &up) - 0.9f64).abs() < 1e-10f64);
+            // 裏から当たる光は無視する
+            assert_eq!(0f64, light.get_diffuse_level(&point(0f64, 0f64, 0f64), &vector(0f64, -1f64, 0f64)));
+            // 環境光は向きに関係なく照らす
+            let ambient = Light::ambient(&white(), 0.1f64);
+            assert_eq!(0.1f64, ambient.get_diffuse_level(&point(0f64, 0f64, 0f64), &vector(0f64, -1f64, 0f64)));
+            // 平行光源はどこでも同じ
+            let sun = Light::directional(&vector(0f64, -1f64, -1f64), &white(), 1f64);
+            let a = sun.get_diffuse_level(&point(0f64, 0f64, 0f64), &up);
+            let b = sun.get_diffuse_level(&point(100f64, -50f64, 7f64), &up);
+            assert!((a - 0.5f64.sqrt()).abs() < 1e-10f64);
+            assert!((a - b).abs() < 1e-10f64);
+        }
+    }
+
+    #[test]
+    fn attenuation() {
+        {
+            let attenuation = Attenuation::new(1f64, 0f64, 1f64);
+            let light = Light::point(&point(0f64, 0f64, 0f64), &attenuation, &white(), 1f64);
+            let up = vector(0f64, 1f64, 0f64);
+            // 距離の2乗で暗くなる
+            assert!((light.get_diffuse_level(&point(0f64, -1f64, 0f64), &up) - 0.5f64).abs() < 1e-10f64);
+            assert!((light.get_diffuse_level(&point(0f64, -3f64, 0f64), &up) - 0.1f64).abs() < 1e-10f64);
+            // スポットライトも同じように弱まる
+            let spot = Light::spot(&point(0f64, 0f64, 0f64), &vector(0f64, -1f64, 0f64), 0.5f64, 0.6f64,
+                &attenuation, &white(), 1f64);
+            assert!((spot.get_diffuse_level(&point(0f64, -3f64, 0f64), &up) - 0.1f64).abs() < 1e-10f64);
+            // 1次の項は距離に比例して弱める
+            assert!((Attenuation::new(1f64, 0.5f64, 0f64).factor(2f64) - 0.5f64).abs() < 1e-10f64);
+        }
+    }
+
+    #[test]
+    fn spot() {
+        {
+            // 真下に向けた円錐(20度までは同じ明るさ、30度で消える)
+            let light = Light::spot(&point(0f64, 10f64, 0f64), &vector(0f64, -1f64, 0f64),
+                20f64.to_radians(), 30f64.to_radians(), &Attenuation::none(), &white(), 1f64);
+            let up = vector(0f64, 1f64, 0f64);
+            let level = |x: f64| light.get_diffuse_level(&point(x, 0f64, 0f64), &up);
+            let cos = |x: f64| 10f64 / (100f64 + x * x).sqrt();
+            assert!((level(0f64) - 1f64).abs() < 1e-10f64);
+            assert!((level(3f64) - cos(3f64)).abs() < 1e-10f64);
+            // 境目では少しだけ暗く、外側は照らさない
+            let x = 10f64 * 25f64.to_radians().tan();
+            assert!(level(x) > 0f64 && level(x) < cos(x));
+            assert_eq!(0f64, level(10f64));
+        }
+    }
+
+    #[test]
+    fn specular() {
+        {
+            let light = Light::point(&point(-10f64, 10f64, 0f64), &Attenuation::none(), &white(), 1f64);
+            let p = point(0f64, 0f64, 0f64);
+            let n = vector(0f64, 1f64, 0f64);
+            for &reflection in [Reflection::Phong, Reflection::BlinnPhong].iter() {
+                // 正反射の方向から見ると最も明るい
+                let mirror = light.get_specular_level(&p, &n, &vector(10f64, 10f64, 0f64), 32f64, reflection);
+                assert!((mirror - 1f64).abs() < 1e-10f64);
+                // 外れると暗くなり、鋭いほど早く暗くなる
+                let eye = vector(10f64, 20f64, 0f64);
+                let dull = light.get_specular_level(&p, &n, &eye, 8f64, reflection);
+                let sharp = light.get_specular_level(&p, &n, &eye, 64f64, reflection);
+                assert!(dull < 1f64 && sharp < dull);
+                // 光源の反対側の面は光らない
+                assert_eq!(0f64, light.get_specular_level(&p, &vector(0f64, -1f64, 0f64), &eye, 8f64, reflection));
+            }
+            // 同じ角度ならBlinn-Phongの方がハイライトが広い
+            let eye = vector(10f64, 20f64, 0f64);
+            let phong = light.get_specular_level(&p, &n, &eye, 16f64, Reflection::Phong);
+            let blinn = light.get_specular_level(&p, &n, &eye, 16f64, Reflection::BlinnPhong);
+            assert!(blinn > phong);
+            // 環境光は光らない
+            let ambient = Light::ambient(&white(), 1f64);
+            assert_eq!(0f64, ambient.get_specular_level(&p, &n, &eye, 16f64, Reflection::Phong));
+        }
+    }
+
+    #[test]
+    fn shade() {
+        {
+            let lighting = Lighting::new(vec![
+                Light::ambient(&white(), 0.1f64),
+                Light::point(&point(0f64, 10f64, 0f64), &Attenuation::none(), &white(), 0.9f64)]);
+            let mut material = Material::new("m", &rgb(0.2f64, 0f64, 0f64));
+            material.specular = white();
+            material.shininess = 16f64;
+            let base = material.diffuse;
+            let p = point(0f64, 0f64, 0f64);
+            let n = vector(0f64, 1f64, 0f64);
+            // 真上から見ると拡散光に白いハイライトが足される(1を超えた分は切り詰める)
+            let c = lighting.shade(&base, &material, &p, &n, &vector(0f64, 1f64, 0f64));
+            assert!(LinearColor::new(1f64, 0.9f64, 0.9f64, 1f64).approx_eq(&c, 1e-10f64), "{:?}", c);
+            // 鏡面反射のない材質は拡散光だけ
+            material.specular = LinearColor::black();
+            let c = lighting.shade(&base, &material, &p, &n, &vector(0f64, 1f64, 0f64));
+            assert!(base.approx_eq(&c, 1e-10f64));
+            // illumが2未満なら鏡面反射はない
+            material.specular = white();
+            material.illum = 1;
+            let c = lighting.shade(&base, &material, &p, &n, &vector(0f64, 1f64, 0f64));
+            assert!(base.approx_eq(&c, 1e-10f64));
+            // 裏からは環境光だけ
+            let c = lighting.shade(&base, &material, &p, &vector(0f64, -1f64, 0f64), &vector(0f64, -1f64, 0f64));
+            assert!((&base * 0.1f64).approx_eq(&c, 1e-10f64));
+            // Kaがあれば環境光はKaに掛ける
+            material.ambient = Some(rgb(0f64, 1f64, 0f64));
+            let c = lighting.shade(&base, &material, &p, &vector(0f64, -1f64, 0f64), &vector(0f64, -1f64, 0f64));
+            assert!(rgb(0f64, 0.1f64, 0f64).approx_eq(&c, 1e-10f64), "{:?}", c);
+            // dは不透明度になる
+            material.dissolve = 0.25f64;
+            let c = lighting.shade(&base, &material, &p, &n, &vector(0f64, 1f64, 0f64));
+            assert!((c.a - 0.25f64).abs() < 1e-10f64);
+        }
+    }
+
+    #[test]
+    fn ambient_level() {
+        {
+            // 環境光は拡散光に含めず別に足す
+            let lighting = Lighting::new(vec![
+                Light::ambient(&rgb(1f64, 0f64, 0f64), 0.5f64),
+                Light::ambient(&white(), 0.1f64),
+                Light::directional(&vector(0f64, -1f64, 0f64), &white(), 1f64)]);
+            let ambient = lighting.get_ambient_level();
+            assert!(rgb(0.6f64, 0.1f64, 0.1f64).approx_eq(&LinearColor::new(ambient.r, ambient.g, ambient.b, 1f64), 1e-10f64));
+            let (diffuse, _) = lighting.get_levels(&point(0f64, 0f64, 0f64), &vector(0f64, 1f64, 0f64),
+                &vector(0f64, 1f64, 0f64), 0f64);
+            assert!(white().approx_eq(&LinearColor::new(diffuse.r, diffuse.g, diffuse.b, 1f64), 1e-10f64));
+        }
+    }
+
+    #[test]
+    fn colored_lights() {
+        {
+            // 赤と青の光源の寄与は足される
+            let lighting = Lighting::new(vec![
+                Light::directional(&vector(0f64, -1f64, 0f64), &rgb(1f64, 0f64, 0f64), 0.5f64),
+                Light::directional(&vector(0f64, -1f64, 0f64), &rgb(0f64, 0f64, 1f64), 1f64)]);
+            let material = Material::new("m", &rgb(0.8f64, 0.8f64, 0.8f64));
+            let c = lighting.shade(&material.diffuse, &material, &point(0f64, 0f64, 0f64),
+                &vector(0f64, 1f64, 0f64), &vector(0f64, 1f64, 0f64));
+            assert!(rgb(0.4f64, 0f64, 0.8f64).approx_eq(&c, 1e-10f64));
+        }
+    }
+
+    #[test]
+    fn mtl_reflectance() {
+        {
+            // MTLのKdは線形な反射率なので、強さ1の光を正面から当てるとそのままの値になる
+            let library = MtlLibrary::parse("newmtl m\nKd 0.5 0.5 0.5\n".as_bytes()).unwrap();
+            let material = library.find("m").unwrap();
+            let lighting = Lighting::new(vec![Light::directional(&vector(0f64, -1f64, 0f64), &white(), 1f64)]);
+            let c = lighting.shade(&material.diffuse, material, &point(0f64, 0f64, 0f64),
+                &vector(0f64, 1f64, 0f64), &vector(0f64, 1f64, 0f64));
+            assert!(rgb(0.5f64, 0.5f64, 0.5f64).approx_eq(&c, 1e-10f64), "{:?}", c);
+        }
     }
 }
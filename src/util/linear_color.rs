@@ -0,0 +1,142 @@
+use std::ops::{Add, Mul};
+use util::color::Color;
+
+// 光の計算に使う色(0〜1の線形な値、aは不透明度)
+// 8bitのColorはsRGBとして扱い、計算の前後で変換する
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LinearColor {
+    pub r: f64,
+    pub g: f64,
+    pub b: f64,
+    pub a: f64
+}
+
+// sRGBの8bit値を線形な値に
+fn srgb_to_linear(c: u8) -> f64 {
+    let c = f64::from(c) / 255.0f64;
+    if c <= 0.04045f64 { c / 12.92f64 } else { ((c + 0.055f64) / 1.055f64).powf(2.4f64) }
+}
+
+// 線形な値をsRGBの8bit値に(範囲外は切り詰める)
+fn linear_to_srgb(c: f64) -> u8 {
+    let c = c.clamp(0.0f64, 1.0f64);
+    let s = if c <= 0.0031308f64 { c * 12.92f64 } else { 1.055f64 * c.powf(1.0f64 / 2.4f64) - 0.055f64 };
+    (s * 255.0f64).round() as u8
+}
+
+impl LinearColor {
+    pub fn new(r: f64, g: f64, b: f64, a: f64) -> LinearColor {
+        LinearColor { r, g, b, a }
+    }
+    pub fn black() -> LinearColor {
+        LinearColor::new(0.0f64, 0.0f64, 0.0f64, 1.0f64)
+    }
+    // 8bitのsRGBから(不透明度はガンマなしでそのまま)
+    pub fn from_srgb(color: &Color) -> LinearColor {
+        LinearColor::new(srgb_to_linear(color.r), srgb_to_linear(color.g), srgb_to_linear(color.b),
+                         f64::from(color.a) / 255.0f64)
+    }
+    // 8bitのsRGBに
+    pub fn to_srgb(self) -> Color {
+        let a = (self.a.clamp(0.0f64, 1.0f64) * 255.0f64).round() as u8;
+        Color::rgba(linear_to_srgb(self.r), linear_to_srgb(self.g), linear_to_srgb(self.b), a)
+    }
+    // selfとotherの間をtで線形補間
+    pub fn lerp(&self, other: &LinearColor, t: f64) -> LinearColor {
+        LinearColor::new(
+            self.r + (other.r - self.r) * t,
+            self.g + (other.g - self.g) * t,
+            self.b + (other.b - self.b) * t,
+            self.a + (other.a - self.a) * t)
+    }
+    // 不透明度に従ってdstの上に重ねる
+    pub fn over(&self, dst: &LinearColor) -> LinearColor {
+        let a = self.a + dst.a * (1.0f64 - self.a);
+        if a <= 0.0f64 {
+            return LinearColor::new(0.0f64, 0.0f64, 0.0f64, 0.0f64);
+        }
+        let mix = |s: f64, d: f64| (s * self.a + d * dst.a * (1.0f64 - self.a)) / a;
+        LinearColor::new(mix(self.r, dst.r), mix(self.g, dst.g), mix(self.b, dst.b), a)
+    }
+    // 各要素を0〜1に収める
+    pub fn clamp(&self) -> LinearColor {
+        let c = |x: f64| x.clamp(0.0f64, 1.0f64);
+        LinearColor::new(c(self.r), c(self.g), c(self.b), c(self.a))
+    }
+    // 各要素の差がeps以下か
+    pub fn approx_eq(&self, rhs: &LinearColor, eps: f64) -> bool {
+        (self.r - rhs.r).abs() <= eps && (self.g - rhs.g).abs() <= eps &&
+            (self.b - rhs.b).abs() <= eps && (self.a - rhs.a).abs() <= eps
+    }
+}
+
+impl<'a> Add<&'a LinearColor> for &'a LinearColor {
+    type Output = LinearColor;
+
+    // 要素ごとの加算(光を重ねる)
+    fn add(self, rhs: &LinearColor) -> LinearColor {
+        LinearColor::new(self.r + rhs.r, self.g + rhs.g, self.b + rhs.b, self.a + rhs.a)
+    }
+}
+
+impl<'a> Mul<&'a LinearColor> for &'a LinearColor {
+    type Output = LinearColor;
+
+    // 要素ごとの乗算(光で照らす)
+    fn mul(self, rhs: &LinearColor) -> LinearColor {
+        LinearColor::new(self.r * rhs.r, self.g * rhs.g, self.b * rhs.b, self.a * rhs.a)
+    }
+}
+
+impl Mul<f64> for &LinearColor {
+    type Output = LinearColor;
+
+    // スカラ値の乗算(不透明度はそのまま)
+    fn mul(self, rhs: f64) -> LinearColor {
+        LinearColor::new(self.r * rhs, self.g * rhs, self.b * rhs, self.a)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn srgb() {
+        {
+            // 8bitに戻すと元の値になる
+            for i in 0..256 {
+                let c = Color::new(i as u8, 255 - i as u8, 0);
+                assert_eq!(c, LinearColor::from_srgb(&c).to_srgb());
+            }
+            // 黒と白はそのまま、中間は暗くなる
+            let c = LinearColor::from_srgb(&Color::new(0, 128, 255));
+            assert!(LinearColor::new(0f64, 0.2158605f64, 1f64, 1f64).approx_eq(&c, 1e-6f64));
+            // 線形な0.5はsRGBでは明るい
+            assert_eq!(Color::new(188, 188, 188), LinearColor::new(0.5f64, 0.5f64, 0.5f64, 1f64).to_srgb());
+            // 範囲外は切り詰める
+            assert_eq!(Color::new(255, 0, 0), LinearColor::new(2f64, -1f64, 0f64, 1f64).to_srgb());
+            // 不透明度はそのまま
+            let c = Color::rgba(0, 0, 0, 128);
+            assert!((LinearColor::from_srgb(&c).a - 128f64 / 255f64).abs() < 1e-10f64);
+            assert_eq!(c, LinearColor::from_srgb(&c).to_srgb());
+        }
+    }
+
+    #[test]
+    fn ops() {
+        {
+            let a = LinearColor::new(0.2f64, 0.4f64, 0.6f64, 1f64);
+            let b = LinearColor::new(0.5f64, 0.5f64, 1f64, 0.5f64);
+            assert!(LinearColor::new(0.7f64, 0.9f64, 1.6f64, 1.5f64).approx_eq(&(&a + &b), 1e-10f64));
+            assert!(LinearColor::new(0.1f64, 0.2f64, 0.6f64, 0.5f64).approx_eq(&(&a * &b), 1e-10f64));
+            assert!(LinearColor::new(0.4f64, 0.8f64, 1.2f64, 1f64).approx_eq(&(&a * 2f64), 1e-10f64));
+            assert!(LinearColor::new(0.35f64, 0.45f64, 0.8f64, 0.75f64).approx_eq(&a.lerp(&b, 0.5f64), 1e-10f64));
+            assert!(a.approx_eq(&a.lerp(&b, 0f64), 1e-10f64));
+            assert!(LinearColor::new(0.7f64, 0.9f64, 1f64, 1f64).approx_eq(&(&a + &b).clamp(), 1e-10f64));
+            // 半透明の色を不透明な色に重ねると不透明になる
+            assert!(LinearColor::new(0.35f64, 0.45f64, 0.8f64, 1f64).approx_eq(&b.over(&a), 1e-10f64));
+            assert!(a.approx_eq(&a.over(&b), 1e-10f64));
+        }
+    }
+}
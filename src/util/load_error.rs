@@ -0,0 +1,50 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+// モデル読み込み時のエラー
+#[derive(Debug)]
+pub enum LoadError {
+    // ファイルが開けない、読めない
+    Io(io::Error),
+    // 書式の誤り(行・列は1始まり)
+    Parse { line: usize, column: usize, message: String },
+    // 存在しない頂点などを参照している
+    IndexOutOfRange { line: usize, column: usize, index: i64, count: usize },
+    // 面が1つもない
+    EmptyMesh,
+    // 画像が読めない
+    Image(String)
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LoadError::Io(ref e) =>
+                write!(f, "I/O error: {}", e),
+            LoadError::Parse { line, column, ref message } =>
+                write!(f, "parse error at line {}, column {}: {}", line, column, message),
+            LoadError::IndexOutOfRange { line, column, index, count } =>
+                write!(f, "index {} out of range (1..{}) at line {}, column {}", index, count, line, column),
+            LoadError::EmptyMesh =>
+                write!(f, "model has no faces"),
+            LoadError::Image(ref message) =>
+                write!(f, "image error: {}", message)
+        }
+    }
+}
+
+impl Error for LoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            LoadError::Io(ref e) => Some(e),
+            _ => None
+        }
+    }
+}
+
+impl From<io::Error> for LoadError {
+    fn from(e: io::Error) -> LoadError {
+        LoadError::Io(e)
+    }
+}
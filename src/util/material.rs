@@ -0,0 +1,52 @@
+use std::rc::Rc;
+
+use util::linear_color::LinearColor;
+use util::texture::Texture;
+
+// マテリアル(MTLファイルのnewmtl)
+// 色はMTLの値(0〜1の線形な反射率)のまま持つ
+#[derive(Debug, Clone)]
+pub struct Material {
+    pub name: String,
+    // 環境光(Ka、なければ拡散光の色を使う)
+    pub ambient: Option<LinearColor>,
+    // 拡散光(Kd)
+    pub diffuse: LinearColor,
+    // 鏡面光(Ks)
+    pub specular: LinearColor,
+    // 鏡面反射の鋭さ(Ns)
+    pub shininess: f64,
+    // 不透明度(d)
+    pub dissolve: f64,
+    // 照明モデル(illum)
+    pub illum: u32,
+    // 拡散光のテクスチャ(map_Kd)
+    pub diffuse_map: Option<String>,
+    // 読み込んだテクスチャ
+    pub texture: Option<Rc<Texture>>
+}
+
+impl Material {
+    pub fn new(name: &str, diffuse: &LinearColor) -> Material {
+        Material {
+            name: name.to_string(),
+            ambient: None,
+            diffuse: *diffuse,
+            specular: LinearColor::black(),
+            shininess: 0.0f64,
+            dissolve: 1.0f64,
+            // illumがなければKsを使う
+            illum: 2,
+            diffuse_map: None,
+            texture: None
+        }
+    }
+    // 環境光の反射率(colorはテクスチャを掛けた拡散光の色)
+    pub fn ambient_color(&self, color: &LinearColor) -> LinearColor {
+        self.ambient.unwrap_or(*color)
+    }
+    // 鏡面反射の色(illumが0と1は鏡面反射なし)
+    pub fn specular_color(&self) -> LinearColor {
+        if self.illum >= 2 { self.specular } else { LinearColor::black() }
+    }
+}
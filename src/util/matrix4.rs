@@ -1,12 +1,13 @@
 use std::ops::{Add, Sub, Mul};
 use util::vector3::Vector3;
+use util::point3::Point3;
 
 // 以下の順序で要素を渡す
 // |  0 |  1 |  2 |  3 |
 // |  4 |  5 |  6 |  7 |
 // |  8 |  9 | 10 | 11 |
 // | 12 | 13 | 14 | 15 |
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Matrix4 {
     pub xs: [f64; 16]
 }
@@ -19,18 +20,242 @@ impl Matrix4 {
     // Vecから作成
     pub fn new_from_vec(xs: Vec<f64>) -> Matrix4 {
         let mut ys = [0.0f64; 16];
-        for i in 0..xs.len() {
-            ys[i] = xs[i];
-        }
+        ys[..xs.len()].copy_from_slice(&xs);
         Matrix4::new(ys)
     }
+    // 各要素の差がeps以下か
+    pub fn approx_eq(&self, rhs: &Matrix4, eps: f64) -> bool {
+        self.xs.iter().zip(rhs.xs.iter()).all(|(&a, &b)| (a - b).abs() <= eps)
+    }
+    // 単位行列
+    pub fn identity() -> Matrix4 {
+        Matrix4::new(
+            [1.0f64, 0.0f64, 0.0f64, 0.0f64,
+                0.0f64, 1.0f64, 0.0f64, 0.0f64,
+                0.0f64, 0.0f64, 1.0f64, 0.0f64,
+                0.0f64, 0.0f64, 0.0f64, 1.0f64]
+        )
+    }
+    // 移動
+    // | 1 | 0 | 0 | tx |
+    // | 0 | 1 | 0 | ty |
+    // | 0 | 0 | 1 | tz |
+    // | 0 | 0 | 0 | 1  |
+    pub fn translation(v: &Vector3) -> Matrix4 {
+        Matrix4::new(
+            [1.0f64, 0.0f64, 0.0f64, v.x,
+                0.0f64, 1.0f64, 0.0f64, v.y,
+                0.0f64, 0.0f64, 1.0f64, v.z,
+                0.0f64, 0.0f64, 0.0f64, 1.0f64]
+        )
+    }
+    // 拡大・縮小、反転
+    // | sx | 0  | 0  | 0 |
+    // | 0  | sy | 0  | 0 |
+    // | 0  | 0  | sz | 0 |
+    // | 0  | 0  | 0  | 1 |
+    pub fn scaling(v: &Vector3) -> Matrix4 {
+        Matrix4::new(
+            [v.x, 0.0f64, 0.0f64, 0.0f64,
+                0.0f64, v.y, 0.0f64, 0.0f64,
+                0.0f64, 0.0f64, v.z, 0.0f64,
+                0.0f64, 0.0f64, 0.0f64, 1.0f64]
+        )
+    }
+    // X軸周りに回転
+    // | 1 | 0     | 0      | 0 |
+    // | 0 | cos r | -sin r | 0 |
+    // | 0 | sin r | cos r  | 0 |
+    // | 0 | 0     | 0      | 1 |
+    pub fn rotation_x(r: f64) -> Matrix4 {
+        Matrix4::new(
+            [1.0f64, 0.0f64, 0.0f64, 0.0f64,
+                0.0f64, r.cos(), -r.sin(), 0.0f64,
+                0.0f64, r.sin(), r.cos(), 0.0f64,
+                0.0f64, 0.0f64, 0.0f64, 1.0f64]
+        )
+    }
+    // Y軸周りに回転
+    // | cos r  | 0 | sin r | 0 |
+    // | 0      | 1 | 0     | 0 |
+    // | -sin r | 0 | cos r | 0 |
+    // | 0      | 0 | 0     | 1 |
+    pub fn rotation_y(r: f64) -> Matrix4 {
+        Matrix4::new(
+            [r.cos(), 0.0f64, r.sin(), 0.0f64,
+                0.0f64, 1.0f64, 0.0f64, 0.0f64,
+                -r.sin(), 0.0f64, r.cos(), 0.0f64,
+                0.0f64, 0.0f64, 0.0f64, 1.0f64]
+        )
+    }
+    // Z軸周りに回転
+    // | cos r | -sin r | 0 | 0 |
+    // | sin r | cos r  | 0 | 0 |
+    // | 0     | 0      | 1 | 0 |
+    // | 0     | 0      | 0 | 1 |
+    pub fn rotation_z(r: f64) -> Matrix4 {
+        Matrix4::new(
+            [r.cos(), -r.sin(), 0.0f64, 0.0f64,
+                r.sin(), r.cos(), 0.0f64, 0.0f64,
+                0.0f64, 0.0f64, 1.0f64, 0.0f64,
+                0.0f64, 0.0f64, 0.0f64, 1.0f64]
+        )
+    }
+    // 任意の軸(単位ベクトル)周りに回転
+    // | c + x x (1 - c)   | x y (1 - c) - z s | x z (1 - c) + y s | 0 |
+    // | y x (1 - c) + z s | c + y y (1 - c)   | y z (1 - c) - x s | 0 |
+    // | z x (1 - c) - y s | z y (1 - c) + x s | c + z z (1 - c)   | 0 |
+    // | 0                 | 0                 | 0                 | 1 |
+    // c = cos r, s = sin r
+    pub fn rotation_axis_angle(axis: &Vector3, r: f64) -> Matrix4 {
+        let a = axis.normalize();
+        let (x, y, z) = (a.x, a.y, a.z);
+        let c = r.cos();
+        let s = r.sin();
+        let t = 1.0f64 - c;
+        Matrix4::new(
+            [c + x * x * t, x * y * t - z * s, x * z * t + y * s, 0.0f64,
+                y * x * t + z * s, c + y * y * t, y * z * t - x * s, 0.0f64,
+                z * x * t - y * s, z * y * t + x * s, c + z * z * t, 0.0f64,
+                0.0f64, 0.0f64, 0.0f64, 1.0f64]
+        )
+    }
+    // eyeからtargetを見るビュー変換(targetが+Z方向、upが+Y方向になる)
+    // | x.x | x.y | x.z | -eye・x |
+    // | y.x | y.y | y.z | -eye・y |
+    // | z.x | z.y | z.z | -eye・z |
+    // | 0   | 0   | 0   | 1       |
+    pub fn look_at(eye: &Point3, target: &Point3, up: &Vector3) -> Matrix4 {
+        let z = (&Vector3::from(*target) - eye).normalize();
+        let x = (up * &z).normalize();
+        let y = (&z * &x).normalize();
+        let p = Vector3::from(*eye);
+        Matrix4::new(
+            [x.x, x.y, x.z, -p.dot(&x),
+                y.x, y.y, y.z, -p.dot(&y),
+                z.x, z.y, z.z, -p.dot(&z),
+                0.0f64, 0.0f64, 0.0f64, 1.0f64]
+        )
+    }
+    // 透視投影(+Z方向を見る。fovは縦の視野角、aspectは幅 / 高さ)
+    // wで割るとzはnearで-1、farで1になる
+    // | f / aspect | 0 | 0                           | 0                          |
+    // | 0          | f | 0                           | 0                          |
+    // | 0          | 0 | (far + near) / (far - near) | -2 far near / (far - near) |
+    // | 0          | 0 | 1                           | 0                          |
+    // f = 1 / tan(fov / 2)
+    pub fn perspective(fov: f64, aspect: f64, near: f64, far: f64) -> Matrix4 {
+        let f = 1.0f64 / (fov / 2.0f64).tan();
+        Matrix4::new(
+            [f / aspect, 0.0f64, 0.0f64, 0.0f64,
+                0.0f64, f, 0.0f64, 0.0f64,
+                0.0f64, 0.0f64, (far + near) / (far - near), -2.0f64 * far * near / (far - near),
+                0.0f64, 0.0f64, 1.0f64, 0.0f64]
+        )
+    }
+    // 平行投影(+Z方向を見る。width、heightは見える範囲の幅と高さ)
+    // | 2 / width | 0          | 0                 | 0                                |
+    // | 0         | 2 / height | 0                 | 0                                |
+    // | 0         | 0          | 2 / (far - near)  | -(far + near) / (far - near)     |
+    // | 0         | 0          | 0                 | 1                                |
+    pub fn orthographic(width: f64, height: f64, near: f64, far: f64) -> Matrix4 {
+        Matrix4::new(
+            [2.0f64 / width, 0.0f64, 0.0f64, 0.0f64,
+                0.0f64, 2.0f64 / height, 0.0f64, 0.0f64,
+                0.0f64, 0.0f64, 2.0f64 / (far - near), -(far + near) / (far - near),
+                0.0f64, 0.0f64, 0.0f64, 1.0f64]
+        )
+    }
+    // 転置行列
+    pub fn transpose(&self) -> Matrix4 {
+        let mut xs = [0.0f64; 16];
+        for y in 0..4 {
+            for x in 0..4 {
+                xs[x * 4 + y] = self.xs[y * 4 + x];
+            }
+        }
+        Matrix4::new(xs)
+    }
+    // 行列式(ガウスの消去法)
+    pub fn determinant(&self) -> f64 {
+        let mut a = self.xs;
+        let mut det = 1.0f64;
+        for col in 0..4 {
+            let pivot = pivot_row(&a, col);
+            if a[pivot * 4 + col] == 0.0f64 {
+                return 0.0f64;
+            }
+            if pivot != col {
+                for x in 0..4 {
+                    a.swap(pivot * 4 + x, col * 4 + x);
+                }
+                det = -det;
+            }
+            det *= a[col * 4 + col];
+            for y in (col + 1)..4 {
+                let f = a[y * 4 + col] / a[col * 4 + col];
+                for x in col..4 {
+                    a[y * 4 + x] -= f * a[col * 4 + x];
+                }
+            }
+        }
+        det
+    }
+    // 逆行列(ガウス・ジョルダン法、逆行列がなければNone)
+    pub fn inverse(&self) -> Option<Matrix4> {
+        let mut a = self.xs;
+        let mut b = Matrix4::identity().xs;
+        // 小さすぎる軸の判定は一番大きい要素に対する比で行う
+        let scale = a.iter().fold(0.0f64, |m, x| m.max(x.abs()));
+        if !scale.is_finite() || scale == 0.0f64 {
+            return None;
+        }
+        for col in 0..4 {
+            let pivot = pivot_row(&a, col);
+            // NaNもここで弾く
+            let p = a[pivot * 4 + col].abs();
+            if p.is_nan() || p <= scale * 1e-12f64 {
+                return None;
+            }
+            for x in 0..4 {
+                a.swap(pivot * 4 + x, col * 4 + x);
+                b.swap(pivot * 4 + x, col * 4 + x);
+            }
+            let p = a[col * 4 + col];
+            for x in 0..4 {
+                a[col * 4 + x] /= p;
+                b[col * 4 + x] /= p;
+            }
+            for y in 0..4 {
+                if y != col {
+                    let f = a[y * 4 + col];
+                    for x in 0..4 {
+                        a[y * 4 + x] -= f * a[col * 4 + x];
+                        b[y * 4 + x] -= f * b[col * 4 + x];
+                    }
+                }
+            }
+        }
+        Some(Matrix4::new(b))
+    }
+}
+
+// 絶対値の一番大きい行を軸にする(NaNは選ばない)
+fn pivot_row(a: &[f64; 16], col: usize) -> usize {
+    let mut pivot = col;
+    for y in (col + 1)..4 {
+        if a[y * 4 + col].abs() > a[pivot * 4 + col].abs() {
+            pivot = y;
+        }
+    }
+    pivot
 }
 
 impl<'a> Add<&'a Matrix4> for &'a Matrix4 {
     type Output = Matrix4;
 
     // 行列同士の加算
-    fn add<'b>(self, rhs: &'b Matrix4) -> Matrix4 {
+    fn add(self, rhs: &Matrix4) -> Matrix4 {
         let v = self.xs
             .iter()
             .zip(rhs.xs.iter())
@@ -44,7 +269,7 @@ impl<'a> Sub<&'a Matrix4> for &'a Matrix4 {
     type Output = Matrix4;
 
     // 行列同士の減算
-    fn sub<'b>(self, rhs: &'b Matrix4) -> Matrix4 {
+    fn sub(self, rhs: &Matrix4) -> Matrix4 {
         let v = self.xs
             .iter()
             .zip(rhs.xs.iter())
@@ -54,7 +279,7 @@ impl<'a> Sub<&'a Matrix4> for &'a Matrix4 {
     }
 }
 
-impl<'a> Mul<f64> for &'a Matrix4 {
+impl Mul<f64> for &Matrix4 {
     type Output = Matrix4;
 
     // スカラ値との乗算
@@ -71,7 +296,7 @@ impl<'a> Mul<&'a Vector3> for &'a Matrix4 {
     type Output = Vector3;
 
     //ベクトルとの積(ドット積)
-    fn mul<'b>(self, v: &'b Vector3) -> Vector3 {
+    fn mul(self, v: &Vector3) -> Vector3 {
         Vector3::new(
             self.xs[0] * v.x + self.xs[1] * v.y + self.xs[2] * v.z + self.xs[3] * v.w,
             self.xs[4] * v.x + self.xs[5] * v.y + self.xs[6] * v.z + self.xs[7] * v.w,
@@ -85,11 +310,11 @@ impl<'a> Mul<&'a Matrix4> for &'a Matrix4 {
     type Output = Matrix4;
 
     // 行列との積(ドット積)
-    fn mul<'b>(self, mat: &'b Matrix4) -> Matrix4 {
+    fn mul(self, mat: &Matrix4) -> Matrix4 {
         let mut xs: [f64; 16] = [0.0f64; 16];
         for y in 0..4 {
             for x in 0..4 {
-                xs[y * 4 + x] = self.xs[y * 4 + 0] * mat.xs[x + 0] +
+                xs[y * 4 + x] = self.xs[y * 4] * mat.xs[x] +
                     self.xs[y * 4 + 1] * mat.xs[x + 4] +
                     self.xs[y * 4 + 2] * mat.xs[x + 8] +
                     self.xs[y * 4 + 3] * mat.xs[x + 12];
@@ -102,6 +327,7 @@ impl<'a> Mul<&'a Matrix4> for &'a Matrix4 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::f64::consts::PI;
 
     #[test]
     fn add() {
@@ -117,7 +343,7 @@ mod tests {
                     11f64, 10f64, 9f64, 8f64,
                     7f64, 6f64, 5f64, 4f64,
                     3f64, 2f64, 1f64, 0f64]);
-            let c = a + b;
+            let c = &a + &b;
             let d = Matrix4::new(
                 [16f64, 16f64, 16f64, 16f64,
                     16f64, 16f64, 16f64, 16f64,
@@ -141,7 +367,7 @@ mod tests {
                     11f64, 10f64, 9f64, 8f64,
                     7f64, 6f64, 5f64, 4f64,
                     3f64, 2f64, 1f64, 0f64]);
-            let c = a - b;
+            let c = &a - &b;
             let d = Matrix4::new(
                 [-14f64, -12f64, -10f64, -8f64,
                     -6f64, -4f64, -2f64, 0f64,
@@ -160,7 +386,7 @@ mod tests {
                     5f64, 6f64, 7f64, 8f64,
                     9f64, 10f64, 11f64, 12f64,
                     13f64, 14f64, 15f64, 16f64]);
-            let b = a * 3f64;
+            let b = &a * 3f64;
             let c = Matrix4::new(
                 [3f64, 6f64, 9f64, 12f64,
                     15f64, 18f64, 21f64, 24f64,
@@ -186,7 +412,7 @@ matrix([[ 30,  70, 110, 150]])
                     9f64, 10f64, 11f64, 12f64,
                     13f64, 14f64, 15f64, 16f64]);
             let b = Vector3::new(1f64, 2f64, 3f64, 4f64);
-            let c = a * b;
+            let c = &a * &b;
             let d = Vector3::new(30f64, 70f64, 110f64, 150f64);
             assert_eq!(c, d);
         }
@@ -215,7 +441,7 @@ matrix([[100, 110, 120, 130],
                     6f64, 7f64, 8f64, 9f64,
                     10f64, 11f64, 12f64, 13f64,
                     14f64, 15f64, 16f64, 17f64]);
-            let c = a * b;
+            let c = &a * &b;
             let d = Matrix4::new(
                 [100f64, 110f64, 120f64, 130f64,
                 228f64, 254f64, 280f64, 306f64,
@@ -224,4 +450,171 @@ matrix([[100, 110, 120, 130],
             assert_eq!(c, d);
         }
     }
-}
\ No newline at end of file
+
+    // 要素ごとにほぼ等しいか
+    fn assert_matrix(expected: &Matrix4, actual: &Matrix4) {
+        assert!(expected.approx_eq(actual, 1e-10f64), "expected {:?}, actual {:?}", expected, actual);
+    }
+
+    // 点を変換して(wで割って)ほぼ等しいか
+    fn assert_point(mat: &Matrix4, p: [f64; 3], expected: [f64; 3]) {
+        let q = Point3::new(p[0], p[1], p[2], 1.0f64).affin(mat);
+        let actual = Point3::new(q.x / q.w, q.y / q.w, q.z / q.w, 1.0f64);
+        let expected = Point3::new(expected[0], expected[1], expected[2], 1.0f64);
+        assert!(expected.approx_eq(&actual, 1e-10f64), "{:?} -> expected {:?}, actual {:?}", p, expected, actual);
+    }
+
+    #[test]
+    fn identity() {
+        {
+            let a = Matrix4::new(
+                [1f64, 2f64, 3f64, 4f64,
+                    5f64, 6f64, 7f64, 8f64,
+                    9f64, 10f64, 11f64, 12f64,
+                    13f64, 14f64, 15f64, 16f64]);
+            assert_matrix(&a, &(&a * &Matrix4::identity()));
+            assert_matrix(&a, &(&Matrix4::identity() * &a));
+        }
+    }
+
+    #[test]
+    fn translation_scaling() {
+        {
+            let t = Matrix4::translation(&Vector3::new(1f64, 2f64, 3f64, 0f64));
+            assert_point(&t, [1f64, 1f64, 1f64], [2f64, 3f64, 4f64]);
+            let s = Matrix4::scaling(&Vector3::new(2f64, -1f64, 0.5f64, 0f64));
+            assert_point(&s, [1f64, 1f64, 1f64], [2f64, -1f64, 0.5f64]);
+            // 方向ベクトル(w = 0)は移動しない
+            let v = &t * &Vector3::new(1f64, 0f64, 0f64, 0f64);
+            assert_eq!((1f64, 0f64, 0f64), (v.x, v.y, v.z));
+        }
+    }
+
+    #[test]
+    fn rotation() {
+        {
+            let r = PI / 2f64;
+            // 右手系で軸の正の向きから見て反時計回り
+            assert_point(&Matrix4::rotation_x(r), [0f64, 1f64, 0f64], [0f64, 0f64, 1f64]);
+            assert_point(&Matrix4::rotation_y(r), [0f64, 0f64, 1f64], [1f64, 0f64, 0f64]);
+            assert_point(&Matrix4::rotation_z(r), [1f64, 0f64, 0f64], [0f64, 1f64, 0f64]);
+            // 任意の軸周りの回転は各軸周りの回転と一致する
+            let x = Vector3::new(2f64, 0f64, 0f64, 0f64);
+            let y = Vector3::new(0f64, 1f64, 0f64, 0f64);
+            let z = Vector3::new(0f64, 0f64, 3f64, 0f64);
+            assert_matrix(&Matrix4::rotation_x(0.3f64), &Matrix4::rotation_axis_angle(&x, 0.3f64));
+            assert_matrix(&Matrix4::rotation_y(0.3f64), &Matrix4::rotation_axis_angle(&y, 0.3f64));
+            assert_matrix(&Matrix4::rotation_z(0.3f64), &Matrix4::rotation_axis_angle(&z, 0.3f64));
+            // (1, 1, 1)周りに120度回すと軸が入れ替わる
+            let a = Matrix4::rotation_axis_angle(&Vector3::new(1f64, 1f64, 1f64, 0f64), PI * 2f64 / 3f64);
+            assert_point(&a, [1f64, 0f64, 0f64], [0f64, 1f64, 0f64]);
+        }
+    }
+
+    #[test]
+    fn look_at() {
+        {
+            let eye = Point3::new(0f64, 0f64, -10f64, 1f64);
+            let target = Point3::new(0f64, 0f64, 0f64, 1f64);
+            let up = Vector3::new(0f64, 1f64, 0f64, 0f64);
+            let m = Matrix4::look_at(&eye, &target, &up);
+            // 視点が原点、注視点が+Z方向
+            assert_point(&m, [0f64, 0f64, -10f64], [0f64, 0f64, 0f64]);
+            assert_point(&m, [0f64, 0f64, 0f64], [0f64, 0f64, 10f64]);
+            assert_point(&m, [1f64, 2f64, 0f64], [1f64, 2f64, 10f64]);
+            // 横から見る
+            let eye = Point3::new(10f64, 0f64, 0f64, 1f64);
+            let m = Matrix4::look_at(&eye, &target, &up);
+            assert_point(&m, [0f64, 0f64, 0f64], [0f64, 0f64, 10f64]);
+            assert_point(&m, [0f64, 0f64, 1f64], [1f64, 0f64, 10f64]);
+        }
+    }
+
+    #[test]
+    fn projection() {
+        {
+            // 透視投影: nearでz = -1、farでz = 1、視野角の端でy = 1
+            let p = Matrix4::perspective(PI / 2f64, 2f64, 1f64, 10f64);
+            assert_point(&p, [0f64, 0f64, 1f64], [0f64, 0f64, -1f64]);
+            assert_point(&p, [0f64, 0f64, 10f64], [0f64, 0f64, 1f64]);
+            assert_point(&p, [0f64, 5f64, 5f64], [0f64, 1f64, 7f64 / 9f64]);
+            assert_point(&p, [10f64, 0f64, 5f64], [1f64, 0f64, 7f64 / 9f64]);
+            // 平行投影: 奥行きで大きさが変わらない
+            let o = Matrix4::orthographic(4f64, 2f64, 1f64, 3f64);
+            assert_point(&o, [2f64, 1f64, 1f64], [1f64, 1f64, -1f64]);
+            assert_point(&o, [2f64, 1f64, 3f64], [1f64, 1f64, 1f64]);
+        }
+    }
+
+    #[test]
+    fn transpose() {
+        {
+            let a = Matrix4::new(
+                [1f64, 2f64, 3f64, 4f64,
+                    5f64, 6f64, 7f64, 8f64,
+                    9f64, 10f64, 11f64, 12f64,
+                    13f64, 14f64, 15f64, 16f64]);
+            let b = Matrix4::new(
+                [1f64, 5f64, 9f64, 13f64,
+                    2f64, 6f64, 10f64, 14f64,
+                    3f64, 7f64, 11f64, 15f64,
+                    4f64, 8f64, 12f64, 16f64]);
+            assert_matrix(&b, &a.transpose());
+            assert_matrix(&a, &a.transpose().transpose());
+        }
+    }
+
+    #[test]
+    fn determinant() {
+        {
+            let a = Matrix4::new(
+                [2f64, 0f64, 1f64, 3f64,
+                    1f64, 1f64, 0f64, 2f64,
+                    0f64, 3f64, 1f64, 1f64,
+                    4f64, 1f64, 2f64, 0f64]);
+            // 余因子展開で求めた値
+            assert!((a.determinant() - -32f64).abs() < 1e-10f64);
+            assert!((Matrix4::identity().determinant() - 1f64).abs() < 1e-10f64);
+            // 行が従属していると0
+            let b = Matrix4::new(
+                [1f64, 2f64, 3f64, 4f64,
+                    5f64, 6f64, 7f64, 8f64,
+                    9f64, 10f64, 11f64, 12f64,
+                    13f64, 14f64, 15f64, 16f64]);
+            assert!(b.determinant().abs() < 1e-10f64);
+        }
+    }
+
+    #[test]
+    fn inverse() {
+        {
+            let a = Matrix4::new(
+                [2f64, 0f64, 1f64, 3f64,
+                    1f64, 1f64, 0f64, 2f64,
+                    0f64, 3f64, 1f64, 1f64,
+                    4f64, 1f64, 2f64, 0f64]);
+            let b = a.inverse().unwrap();
+            assert_matrix(&Matrix4::identity(), &(&a * &b));
+            assert_matrix(&Matrix4::identity(), &(&b * &a));
+            // 移動の逆は逆向きの移動
+            let t = Matrix4::translation(&Vector3::new(1f64, 2f64, 3f64, 0f64));
+            assert_matrix(&Matrix4::translation(&Vector3::new(-1f64, -2f64, -3f64, 0f64)), &t.inverse().unwrap());
+            // 逆行列がない
+            let c = Matrix4::new(
+                [1f64, 2f64, 3f64, 4f64,
+                    5f64, 6f64, 7f64, 8f64,
+                    9f64, 10f64, 11f64, 12f64,
+                    13f64, 14f64, 15f64, 16f64]);
+            assert!(c.inverse().is_none());
+            // 小さい拡大縮小にも逆行列がある
+            let s = Matrix4::scaling(&Vector3::new(1e-4f64, 1e-4f64, 1e-4f64, 0f64));
+            assert_matrix(&Matrix4::identity(), &(&s * &s.inverse().unwrap()));
+            // 大きい値でも特異な行列は特異
+            assert!((&c * 1e6f64).inverse().is_none());
+            // NaNを含んでもパニックしない
+            let mut n = Matrix4::identity();
+            n.xs[5] = f64::NAN;
+            assert!(n.inverse().is_none());
+        }
+    }
+}
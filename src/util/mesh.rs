@@ -0,0 +1,195 @@
+use std::collections::HashMap;
+
+use util::point3::Point3;
+use util::vector3::Vector3;
+use util::tex_coord::TexCoord;
+use util::material::Material;
+use util::mtl::MtlLibrary;
+use util::obj::ObjModel;
+
+// 頂点
+#[derive(Debug, Copy, Clone)]
+pub struct Vertex {
+    pub position: Point3,
+    pub normal: Option<Vector3>,
+    pub tex_coord: Option<TexCoord>
+}
+
+impl Vertex {
+    pub fn new(position: &Point3, normal: Option<Vector3>, tex_coord: Option<TexCoord>) -> Vertex {
+        Vertex { position: *position, normal, tex_coord }
+    }
+}
+
+// 三角形(頂点バッファへの添字)
+#[derive(Debug, Copy, Clone)]
+pub struct Triangle {
+    pub indices: [usize; 3],
+    // Mesh::materialsへの添字
+    pub material: usize
+}
+
+impl Triangle {
+    pub fn new(indices: [usize; 3], material: usize) -> Triangle {
+        Triangle { indices, material }
+    }
+}
+
+// 頂点を共有するポリゴン群
+#[derive(Debug, Clone)]
+pub struct Mesh {
+    pub vertices: Vec<Vertex>,
+    pub triangles: Vec<Triangle>,
+    pub materials: Vec<Material>
+}
+
+impl Mesh {
+    pub fn new(vertices: Vec<Vertex>, triangles: Vec<Triangle>, materials: Vec<Material>) -> Mesh {
+        Mesh { vertices, triangles, materials }
+    }
+
+    // OBJファイルの内容から作成
+    // 座標・テクスチャ座標・法線の組が同じ頂点は共有する
+    // マテリアルの指定がない面やライブラリにないマテリアルはdefaultを使う
+    pub fn from_obj(model: &ObjModel, libraries: &[MtlLibrary], default: &Material) -> Mesh {
+        let mut materials = vec![default.clone()];
+        let material_indices: Vec<usize> = model.materials.iter()
+            .map(|name| match libraries.iter().filter_map(|l| l.find(name)).next() {
+                Some(material) => {
+                    materials.push(material.clone());
+                    materials.len() - 1
+                }
+                None => 0
+            })
+            .collect();
+        let mut vertices: Vec<Vertex> = Vec::with_capacity(model.vertices.len());
+        let mut shared: HashMap<(usize, Option<usize>, Option<usize>), usize> = HashMap::new();
+        let mut triangles = Vec::new();
+        for (t, material) in model.triangles().iter() {
+            let mut indices = [0usize; 3];
+            for (i, index) in t.iter().enumerate() {
+                let key = (index.v, index.vt, index.vn);
+                indices[i] = *shared.entry(key).or_insert_with(|| {
+                    vertices.push(Vertex::new(
+                        &model.vertices[index.v],
+                        // 長さ0の法線は無いものとしてcompute_normalsで求める
+                        index.vn.map(|n| model.normals[n]).filter(|n| n.norm() > 0.0f64).map(|n| n.normalize()),
+                        index.vt.map(|t| model.tex_coords[t])));
+                    vertices.len() - 1
+                });
+            }
+            triangles.push(Triangle::new(indices, material.map_or(0, |m| material_indices[m])));
+        }
+        Mesh::new(vertices, triangles, materials)
+    }
+
+    // 外接する直方体(最小の点と最大の点)
+    pub fn bounds(&self) -> Option<(Point3, Point3)> {
+        let first = self.vertices.first()?.position;
+        Some(self.vertices.iter().fold((first, first), |(min, max), v| {
+            let p = &v.position;
+            (Point3::new(min.x.min(p.x), min.y.min(p.y), min.z.min(p.z), 1.0f64),
+             Point3::new(max.x.max(p.x), max.y.max(p.y), max.z.max(p.z), 1.0f64))
+        }))
+    }
+
+    // 外接する直方体の中心を原点に移動する
+    pub fn center(&mut self) {
+        if let Some((min, max)) = self.bounds() {
+            let center = Vector3::from(&(&min + &max) / 2.0f64);
+            for v in self.vertices.iter_mut() {
+                v.position = &v.position - &center;
+            }
+        }
+    }
+
+    // 法線のない頂点に、同じ位置を共有する面の法線を面積で重み付けして平均したものを入れる
+    pub fn compute_normals(&mut self) {
+        let key = |p: &Point3| (p.x.to_bits(), p.y.to_bits(), p.z.to_bits());
+        let mut sums: HashMap<(u64, u64, u64), Vector3> = HashMap::new();
+        for t in self.triangles.iter() {
+            let p = |i: usize| self.vertices[t.indices[i]].position;
+            // 外積の大きさは面積の2倍なので、そのまま足せば面積で重み付けされる
+            let normal = &Vector3::from(&p(1) - &p(0)) * &Vector3::from(&p(2) - &p(0));
+            for &i in t.indices.iter() {
+                let sum = sums.entry(key(&self.vertices[i].position))
+                    .or_insert_with(|| Vector3::new(0.0f64, 0.0f64, 0.0f64, 0.0f64));
+                *sum = &*sum + &normal;
+            }
+        }
+        for v in self.vertices.iter_mut().filter(|v| v.normal.is_none()) {
+            v.normal = sums.get(&key(&v.position))
+                .filter(|n| n.norm() > 0.0f64)
+                .map(|n| n.normalize());
+        }
+    }
+
+    // 原点から最も遠い頂点までの距離
+    pub fn radius(&self) -> f64 {
+        self.vertices.iter()
+            .map(|v| Vector3::from(v.position).norm())
+            .fold(0.0f64, f64::max)
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use util::linear_color::LinearColor;
+
+    #[test]
+    fn center() {
+        {
+            let vertices = vec![
+                Vertex::new(&Point3::new(1.0f64, 2.0f64, 3.0f64, 1.0f64), None, None),
+                Vertex::new(&Point3::new(3.0f64, 6.0f64, 3.0f64, 1.0f64), None, None),
+                Vertex::new(&Point3::new(1.0f64, 6.0f64, 5.0f64, 1.0f64), None, None)];
+            let mut mesh = Mesh::new(vertices, vec![Triangle::new([0, 1, 2], 0)], Vec::new());
+            mesh.center();
+            let (min, max) = mesh.bounds().unwrap();
+            assert_eq!((-1.0f64, -2.0f64, -1.0f64), (min.x, min.y, min.z));
+            assert_eq!((1.0f64, 2.0f64, 1.0f64), (max.x, max.y, max.z));
+            assert!((mesh.radius() - 6.0f64.sqrt()).abs() < 1e-10f64);
+        }
+    }
+
+    #[test]
+    fn compute_normals() {
+        {
+            // 直角に折れた2枚の面が共有する辺の頂点は、2つの法線の中間を向く
+            let p = |x: f64, y: f64, z: f64| Vertex::new(&Point3::new(x, y, z, 1.0f64), None, None);
+            let vertices = vec![
+                p(0.0f64, 0.0f64, 0.0f64), p(0.0f64, 1.0f64, 0.0f64), p(1.0f64, 0.0f64, 0.0f64),
+                p(0.0f64, 0.0f64, 1.0f64),
+                // 法線があればそのまま
+                Vertex::new(&Point3::new(5.0f64, 5.0f64, 5.0f64, 1.0f64), Some(Vector3::new(1.0f64, 0.0f64, 0.0f64, 0.0f64)), None)];
+            let triangles = vec![Triangle::new([0, 1, 2], 0), Triangle::new([0, 3, 1], 0)];
+            let mut mesh = Mesh::new(vertices, triangles, Vec::new());
+            mesh.compute_normals();
+            let n = mesh.vertices[2].normal.unwrap();
+            assert!((n.z - -1.0f64).abs() < 1e-10f64, "{:?}", n);
+            let n = mesh.vertices[3].normal.unwrap();
+            assert!((n.x - -1.0f64).abs() < 1e-10f64, "{:?}", n);
+            let n = mesh.vertices[0].normal.unwrap();
+            assert!((n.x - n.z).abs() < 1e-10f64 && n.x < 0.0f64, "{:?}", n);
+            assert_eq!(Some(Vector3::new(1.0f64, 0.0f64, 0.0f64, 0.0f64)), mesh.vertices[4].normal);
+        }
+    }
+
+    #[test]
+    fn zero_normal() {
+        {
+            // vn 0 0 0は法線なしとして扱い、面から求め直す
+            let model = ObjModel::parse("v 0 0 0\nv 1 0 0\nv 0 1 0\nvn 0 0 0\nf 1//1 2//1 3//1\n".as_bytes()).unwrap();
+            let default = Material::new("default", &LinearColor::black());
+            let mut mesh = Mesh::from_obj(&model, &[], &default);
+            assert!(mesh.vertices.iter().all(|v| v.normal.is_none()));
+            mesh.compute_normals();
+            for v in mesh.vertices.iter() {
+                let n = v.normal.unwrap();
+                assert!((n.z - 1.0f64).abs() < 1e-10f64, "{:?}", n);
+            }
+        }
+    }
+}
@@ -1,11 +1,25 @@
 pub mod point3;
 pub mod vector3;
 pub mod size2;
+pub mod tex_coord;
 pub mod matrix4;
+pub mod quaternion;
+pub mod transform;
 pub mod polygon3;
+pub mod mesh;
 
 pub mod color;
+pub mod linear_color;
 pub mod camera;
+pub mod fly;
 pub mod light;
 pub mod screen;
 pub mod world;
+pub mod load_error;
+pub mod obj;
+pub mod material;
+pub mod mtl;
+pub mod texture;
+pub mod framebuffer;
+pub mod rasterizer;
+pub mod clipper;
@@ -0,0 +1,214 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use util::linear_color::LinearColor;
+use util::load_error::LoadError;
+use util::material::Material;
+use util::obj::{SkippedLine, tokenize, parse_floats, rest_of_line};
+
+// MTLファイルの内容
+#[derive(Debug, Clone)]
+pub struct MtlLibrary {
+    pub materials: Vec<Material>,
+    pub skipped: Vec<SkippedLine>
+}
+
+impl MtlLibrary {
+    pub fn new() -> MtlLibrary {
+        MtlLibrary { materials: Vec::new(), skipped: Vec::new() }
+    }
+
+    // ファイルから読み込み
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<MtlLibrary, LoadError> {
+        let file = File::open(path)?;
+        MtlLibrary::parse(BufReader::new(file))
+    }
+
+    // 読み込み
+    pub fn parse<R: BufRead>(mut reader: R) -> Result<MtlLibrary, LoadError> {
+        let mut library = MtlLibrary::new();
+        let mut buf: Vec<u8> = Vec::new();
+        let mut line_no = 0usize;
+        loop {
+            buf.clear();
+            if reader.read_until(b'\n', &mut buf)? == 0 {
+                break;
+            }
+            line_no += 1;
+            // マテリアル名はUTF-8とは限らない
+            let line = String::from_utf8_lossy(&buf);
+            let line = line.trim_end_matches(['\n', '\r']);
+            library.parse_line(line, line_no)?;
+        }
+        Ok(library)
+    }
+
+    // 名前で検索
+    pub fn find(&self, name: &str) -> Option<&Material> {
+        self.materials.iter().find(|m| m.name == name)
+    }
+
+    // 1行の解釈
+    fn parse_line(&mut self, line: &str, line_no: usize) -> Result<(), LoadError> {
+        // コメントを除去
+        let content = match line.find('#') {
+            Some(i) => &line[..i],
+            None => line
+        };
+        let tokens = tokenize(content);
+        let (column, keyword) = match tokens.first() {
+            Some(&token) => token,
+            None => return Ok(())
+        };
+        let args = &tokens[1..];
+        if keyword == "newmtl" {
+            let name = rest_of_line(content, args, line_no, column)?;
+            self.materials.push(Material::new(&name, &LinearColor::new(1.0f64, 1.0f64, 1.0f64, 1.0f64)));
+            return Ok(());
+        }
+        let material = match self.materials.last_mut() {
+            Some(material) => material,
+            None => return Err(LoadError::Parse {
+                line: line_no,
+                column,
+                message: format!("'{}' before newmtl", keyword)
+            })
+        };
+        match keyword {
+            "Ka" => material.ambient = Some(parse_color(args, line_no, column)?),
+            "Kd" => material.diffuse = parse_color(args, line_no, column)?,
+            "Ks" => material.specular = parse_color(args, line_no, column)?,
+            "Ns" => material.shininess = parse_floats(args, 1, 1, line_no, column)?[0],
+            "d" => {
+                // -halo(見る向きで変わる不透明度)は普通のdとして扱う
+                let args = match args.first() {
+                    Some(&(_, "-halo")) => &args[1..],
+                    _ => args
+                };
+                material.dissolve = parse_floats(args, 1, 1, line_no, column)?[0];
+            }
+            // Trは透明度(dの逆)
+            "Tr" => material.dissolve = 1.0f64 - parse_floats(args, 1, 1, line_no, column)?[0],
+            "illum" => {
+                let &(column, s) = args.first().ok_or_else(||
+                    LoadError::Parse { line: line_no, column, message: "missing illumination model".to_string() })?;
+                material.illum = s.parse::<u32>().map_err(|_|
+                    LoadError::Parse { line: line_no, column, message: format!("invalid illumination model '{}'", s) })?;
+            }
+            // オプション(-s 1 1 1など)は無視してファイル名だけ使う
+            "map_Kd" => {
+                let name = skip_map_options(args);
+                if name.is_empty() {
+                    return Err(LoadError::Parse { line: line_no, column, message: "missing texture file name".to_string() });
+                }
+                material.diffuse_map = Some(rest_of_line(content, name, line_no, column)?);
+            }
+            // 描画には使わないが正しい文
+            "Ke" | "Ni" | "Tf" | "map_Ka" | "map_Ks" | "map_Ns" | "map_d" | "map_Bump" | "map_bump" | "bump" => (),
+            _ => self.skipped.push(SkippedLine {
+                line: line_no,
+                text: line.to_string(),
+                reason: format!("unsupported statement '{}'", keyword)
+            })
+        }
+        Ok(())
+    }
+}
+
+// テクスチャのオプションを飛ばした残り(ファイル名、空白を含みうる)
+// 知らないオプションはファイル名の一部とみなす
+fn skip_map_options<'a, 'b>(args: &'b [(usize, &'a str)]) -> &'b [(usize, &'a str)] {
+    let mut i = 0;
+    while i < args.len() {
+        // オプションごとの引数の数(最小、最大)
+        let (min, max) = match args[i].1 {
+            "-blendu" | "-blendv" | "-bm" | "-boost" | "-cc" | "-clamp" | "-imfchan" | "-texres" => (1, 1),
+            "-mm" => (2, 2),
+            "-o" | "-s" | "-t" => (1, 3),
+            _ => break
+        };
+        i += 1 + min;
+        // 省略できる引数は数のときだけ読む
+        for _ in min..max {
+            if i < args.len() && args[i].1.parse::<f64>().is_ok() {
+                i += 1;
+            }
+        }
+    }
+    &args[args.len().min(i)..]
+}
+
+// 0.0〜1.0の色(gとbは省略するとrと同じ)
+// 線形な反射率なのでsRGBの変換はしない
+fn parse_color(args: &[(usize, &str)], line: usize, column: usize) -> Result<LinearColor, LoadError> {
+    if let Some(&(column, s)) = args.first() {
+        if s == "spectral" || s == "xyz" {
+            return Err(LoadError::Parse { line, column, message: format!("unsupported color format '{}'", s) });
+        }
+    }
+    let xs = parse_floats(args, 1, 3, line, column)?;
+    let r = xs[0];
+    let g = if xs.len() >= 2 { xs[1] } else { r };
+    let b = if xs.len() >= 3 { xs[2] } else { r };
+    Ok(LinearColor::new(r.max(0.0f64), g.max(0.0f64), b.max(0.0f64), 1.0f64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        {
+            let library = MtlLibrary::parse(
+                "# comment\nnewmtl hair\nKa 0 0 0\nKd 0.5 1.0 0.0\nKs 1 1 1\nNs 96.0\nd 0.5\nillum 2\n\
+                 map_Kd -s 1 1 1 hair.png\nnewmtl skin\nKd 1\nfoo 1\n".as_bytes()).unwrap();
+            assert_eq!(2, library.materials.len());
+            let hair = library.find("hair").unwrap();
+            assert_eq!(Some(LinearColor::black()), hair.ambient);
+            assert_eq!(LinearColor::new(0.5f64, 1.0f64, 0.0f64, 1.0f64), hair.diffuse);
+            assert_eq!(LinearColor::new(1.0f64, 1.0f64, 1.0f64, 1.0f64), hair.specular);
+            assert_eq!(96.0f64, hair.shininess);
+            assert_eq!(0.5f64, hair.dissolve);
+            assert_eq!(2, hair.illum);
+            assert_eq!(Some("hair.png".to_string()), hair.diffuse_map);
+            let skin = library.find("skin").unwrap();
+            assert_eq!(None, skin.ambient);
+            assert_eq!(LinearColor::new(1.0f64, 1.0f64, 1.0f64, 1.0f64), skin.diffuse);
+            assert_eq!(vec![12], library.skipped.iter().map(|s| s.line).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn options() {
+        {
+            // オプションの後ろは空白を含むファイル名
+            let library = MtlLibrary::parse(
+                "newmtl a\nd -halo 0.25\nmap_Kd -o 0.5 -mm 0 1 -clamp on my hair.png\n".as_bytes()).unwrap();
+            let a = library.find("a").unwrap();
+            assert_eq!(0.25f64, a.dissolve);
+            assert_eq!(Some("my hair.png".to_string()), a.diffuse_map);
+            // illumがなければ鏡面反射あり
+            assert_eq!(2, a.illum);
+            match MtlLibrary::parse("newmtl a\nmap_Kd -s 1 1 1\n".as_bytes()) {
+                Err(LoadError::Parse { line: 2, .. }) => (),
+                r => panic!("{:?}", r)
+            }
+        }
+    }
+
+    #[test]
+    fn errors() {
+        {
+            match MtlLibrary::parse("Kd 1 1 1\n".as_bytes()) {
+                Err(LoadError::Parse { line: 1, column: 1, .. }) => (),
+                r => panic!("{:?}", r)
+            }
+            match MtlLibrary::parse("newmtl a\nKd 1 x 1\n".as_bytes()) {
+                Err(LoadError::Parse { line: 2, column: 6, .. }) => (),
+                r => panic!("{:?}", r)
+            }
+        }
+    }
+}
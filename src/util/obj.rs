@@ -0,0 +1,518 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use util::load_error::LoadError;
+use util::point3::Point3;
+use util::vector3::Vector3;
+use util::tex_coord::TexCoord;
+
+// 面を構成する頂点への参照(0始まりに解決済み)
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct FaceIndex {
+    pub v: usize,
+    pub vt: Option<usize>,
+    pub vn: Option<usize>
+}
+
+// 面(3点以上の多角形)
+#[derive(Debug, Clone)]
+pub struct Face {
+    pub indices: Vec<FaceIndex>,
+    // usemtlで指定されたマテリアル(ObjModel::materialsへの添字)
+    pub material: Option<usize>
+}
+
+// 読み飛ばした(未対応の)文や無視した部分
+#[derive(Debug, Clone)]
+pub struct SkippedLine {
+    pub line: usize,
+    pub text: String,
+    pub reason: String
+}
+
+// Wavefront OBJファイルの内容
+#[derive(Debug, Clone)]
+pub struct ObjModel {
+    pub vertices: Vec<Point3>,
+    pub tex_coords: Vec<TexCoord>,
+    pub normals: Vec<Vector3>,
+    pub faces: Vec<Face>,
+    // mtllibで指定されたファイル名
+    pub material_libs: Vec<String>,
+    // usemtlで指定されたマテリアル名(出現順)
+    pub materials: Vec<String>,
+    pub skipped: Vec<SkippedLine>
+}
+
+impl ObjModel {
+    pub fn new() -> ObjModel {
+        ObjModel {
+            vertices: Vec::new(),
+            tex_coords: Vec::new(),
+            normals: Vec::new(),
+            faces: Vec::new(),
+            material_libs: Vec::new(),
+            materials: Vec::new(),
+            skipped: Vec::new()
+        }
+    }
+
+    // ファイルから読み込み
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<ObjModel, LoadError> {
+        let file = File::open(path)?;
+        ObjModel::parse(BufReader::new(file))
+    }
+
+    // 読み込み
+    // 書式の誤りはエラーとし、未対応の文はskippedへ記録する
+    pub fn parse<R: BufRead>(mut reader: R) -> Result<ObjModel, LoadError> {
+        let mut model = ObjModel::new();
+        let mut buf: Vec<u8> = Vec::new();
+        let mut line_no = 0usize;
+        let mut start_no = 0usize;
+        let mut pending = String::new();
+        let mut material: Option<usize> = None;
+        loop {
+            buf.clear();
+            if reader.read_until(b'\n', &mut buf)? == 0 {
+                break;
+            }
+            line_no += 1;
+            // マテリアル名などはUTF-8とは限らない
+            let line = String::from_utf8_lossy(&buf);
+            let line = line.trim_end_matches(['\n', '\r']);
+            if pending.is_empty() {
+                start_no = line_no;
+            }
+            // 行末の'\'は次の行へ続く
+            if let Some(line) = line.strip_suffix('\\') {
+                pending.push_str(line);
+                pending.push(' ');
+                continue;
+            }
+            pending.push_str(line);
+            model.parse_line(&pending, start_no, &mut material)?;
+            pending.clear();
+        }
+        if !pending.is_empty() {
+            model.parse_line(&pending, start_no, &mut material)?;
+        }
+        if model.faces.is_empty() {
+            return Err(LoadError::EmptyMesh);
+        }
+        Ok(model)
+    }
+
+    // 1行(1文)の解釈
+    fn parse_line(&mut self, line: &str, line_no: usize, material: &mut Option<usize>) -> Result<(), LoadError> {
+        // コメントを除去
+        let content = match line.find('#') {
+            Some(i) => &line[..i],
+            None => line
+        };
+        let tokens = tokenize(content);
+        let (column, keyword) = match tokens.first() {
+            Some(&token) => token,
+            None => return Ok(())
+        };
+        let args = &tokens[1..];
+        match keyword {
+            // 頂点データ
+            "v" => {
+                // 4つ目の重み(有理曲線用)と後ろの3つの頂点色は読むだけで使わない
+                // wは同次座標として扱うので常に1にする
+                let xs = parse_floats(args, 3, 7, line_no, column)?;
+                if xs.len() == 5 {
+                    return Err(LoadError::Parse {
+                        line: line_no,
+                        column,
+                        message: "expected x y z [w] [r g b]".to_string()
+                    });
+                }
+                self.vertices.push(Point3::new(xs[0], xs[1], xs[2], 1.0f64));
+            }
+            // テクスチャ座標
+            "vt" => {
+                let xs = parse_floats(args, 1, 3, line_no, column)?;
+                let v = if xs.len() >= 2 { xs[1] } else { 0.0f64 };
+                self.tex_coords.push(TexCoord::new(xs[0], v));
+            }
+            // 法線
+            "vn" => {
+                let xs = parse_floats(args, 3, 3, line_no, column)?;
+                self.normals.push(Vector3::new(xs[0], xs[1], xs[2], 0.0f64));
+            }
+            // ポリゴン
+            "f" => {
+                if args.len() < 3 {
+                    return Err(LoadError::Parse {
+                        line: line_no,
+                        column,
+                        message: format!("face needs at least 3 vertices, found {}", args.len())
+                    });
+                }
+                let mut indices = Vec::with_capacity(args.len());
+                let mut warnings = Vec::new();
+                for &(column, token) in args.iter() {
+                    indices.push(self.parse_face_index(token, line_no, column, &mut warnings)?);
+                }
+                if let Some(reason) = warnings.into_iter().next() {
+                    self.skipped.push(SkippedLine { line: line_no, text: line.to_string(), reason });
+                }
+                self.faces.push(Face { indices, material: *material });
+            }
+            // マテリアルライブラリ
+            "mtllib" => {
+                for &(_, name) in args.iter() {
+                    self.material_libs.push(name.to_string());
+                }
+            }
+            // 以降の面のマテリアル
+            "usemtl" => {
+                let name = rest_of_line(content, args, line_no, column)?;
+                let index = match self.materials.iter().position(|m| *m == name) {
+                    Some(index) => index,
+                    None => {
+                        self.materials.push(name);
+                        self.materials.len() - 1
+                    }
+                };
+                *material = Some(index);
+            }
+            // 描画には使わないが正しい文
+            "o" | "g" | "s" | "vp" => (),
+            _ => self.skipped.push(SkippedLine {
+                line: line_no,
+                text: line.to_string(),
+                reason: format!("unsupported statement '{}'", keyword)
+            })
+        }
+        Ok(())
+    }
+
+    // v, v/vt, v//vn, v/vt/vn
+    fn parse_face_index(&self, token: &str, line: usize, column: usize, warnings: &mut Vec<String>) -> Result<FaceIndex, LoadError> {
+        let parts: Vec<&str> = token.split('/').collect();
+        if parts.len() > 3 || parts[0].is_empty() {
+            return Err(LoadError::Parse { line, column, message: format!("malformed face vertex '{}'", token) });
+        }
+        // '/'の後ろの列
+        let vt_column = column + parts[0].chars().count() + 1;
+        let vn_column = vt_column + parts.get(1).map_or(0, |s| s.chars().count()) + 1;
+        let v = resolve_index(parts[0], self.vertices.len(), line, column)?;
+        // 存在しないテクスチャ座標(vtのないファイルなど)はないものとする
+        let vt = match parts.get(1) {
+            Some(s) if !s.is_empty() => match resolve_index(s, self.tex_coords.len(), line, vt_column) {
+                Ok(vt) => Some(vt),
+                Err(LoadError::IndexOutOfRange { index, count, .. }) => {
+                    warnings.push(format!("texture index {} out of range (1..{}), ignored", index, count));
+                    None
+                }
+                Err(e) => return Err(e)
+            },
+            _ => None
+        };
+        let vn = match parts.get(2) {
+            Some(s) if !s.is_empty() => Some(resolve_index(s, self.normals.len(), line, vn_column)?),
+            _ => None
+        };
+        Ok(FaceIndex { v, vt, vn })
+    }
+
+    // 全ての面を三角形に分割する(マテリアル付き)
+    pub fn triangles(&self) -> Vec<([FaceIndex; 3], Option<usize>)> {
+        let mut triangles = Vec::new();
+        for face in self.faces.iter() {
+            let points: Vec<Point3> = face.indices.iter()
+                .map(|i| self.vertices[i.v])
+                .collect();
+            for t in triangulate(&points) {
+                triangles.push(([face.indices[t[0]], face.indices[t[1]], face.indices[t[2]]], face.material));
+            }
+        }
+        triangles
+    }
+}
+
+// 空白で区切った語とその列(1始まり)
+pub fn tokenize(line: &str) -> Vec<(usize, &str)> {
+    let mut tokens = Vec::new();
+    let mut start: Option<(usize, usize)> = None;
+    let mut column = 0usize;
+    for (i, c) in line.char_indices() {
+        column += 1;
+        match (c.is_whitespace(), start) {
+            (true, Some((begin, col))) => {
+                tokens.push((col, &line[begin..i]));
+                start = None;
+            }
+            (false, None) => start = Some((i, column)),
+            _ => ()
+        }
+    }
+    if let Some((begin, col)) = start {
+        tokens.push((col, &line[begin..]));
+    }
+    tokens
+}
+
+// 数値の並び
+pub fn parse_floats(args: &[(usize, &str)], min: usize, max: usize, line: usize, column: usize) -> Result<Vec<f64>, LoadError> {
+    if args.len() < min || args.len() > max {
+        return Err(LoadError::Parse {
+            line,
+            column,
+            message: format!("expected {} to {} numbers, found {}", min, max, args.len())
+        });
+    }
+    args.iter()
+        .map(|&(column, s)| s.parse::<f64>().map_err(|_|
+            LoadError::Parse { line, column, message: format!("invalid number '{}'", s) }))
+        .collect()
+}
+
+// 名前など空白を含みうる引数(最初の語から行末まで)
+pub fn rest_of_line(content: &str, args: &[(usize, &str)], line: usize, column: usize) -> Result<String, LoadError> {
+    match args.first() {
+        Some(&(first, _)) => {
+            let begin = content.char_indices().nth(first - 1).map_or(content.len(), |(i, _)| i);
+            Ok(content[begin..].trim().to_string())
+        }
+        None => Err(LoadError::Parse { line, column, message: "missing name".to_string() })
+    }
+}
+
+// 1始まりの添字と負の(相対)添字を0始まりに変換
+fn resolve_index(s: &str, count: usize, line: usize, column: usize) -> Result<usize, LoadError> {
+    let index = s.parse::<i64>().map_err(|_|
+        LoadError::Parse { line, column, message: format!("invalid index '{}'", s) })?;
+    let resolved = if index > 0 { index - 1 } else { count as i64 + index };
+    if index == 0 || resolved < 0 || resolved >= count as i64 {
+        return Err(LoadError::IndexOutOfRange { line, column, index, count });
+    }
+    Ok(resolved as usize)
+}
+
+// 多角形を三角形に分割する(耳刈り取り法)
+// 戻り値は引数の点への添字
+pub fn triangulate(points: &[Point3]) -> Vec<[usize; 3]> {
+    let n = points.len();
+    if n < 3 {
+        return Vec::new();
+    }
+    if n == 3 {
+        return vec![[0, 1, 2]];
+    }
+    // Newell法で多角形の法線を求める
+    let mut normal = Vector3::new(0.0f64, 0.0f64, 0.0f64, 0.0f64);
+    for i in 0..n {
+        let a = &points[i];
+        let b = &points[(i + 1) % n];
+        normal.x += (a.y - b.y) * (a.z + b.z);
+        normal.y += (a.z - b.z) * (a.x + b.x);
+        normal.z += (a.x - b.x) * (a.y + b.y);
+    }
+    if normal.norm() < 1e-12f64 {
+        return fan(n);
+    }
+    let normal = normal.normalize();
+    // 多角形の面上の2次元座標に変換(法線方向から見て反時計回りになる)
+    let helper = if normal.x.abs() < 0.9f64 {
+        Vector3::new(1.0f64, 0.0f64, 0.0f64, 0.0f64)
+    } else {
+        Vector3::new(0.0f64, 1.0f64, 0.0f64, 0.0f64)
+    };
+    let u = (&helper * &normal).normalize();
+    let v = &normal * &u;
+    let ps: Vec<(f64, f64)> = points.iter()
+        .map(|p| {
+            let p = Vector3::from(*p);
+            (p.dot(&u), p.dot(&v))
+        })
+        .collect();
+
+    let mut remaining: Vec<usize> = (0..n).collect();
+    let mut triangles = Vec::with_capacity(n - 2);
+    while remaining.len() > 3 {
+        let m = remaining.len();
+        let ear = (0..m).find(|&i| {
+            let a = remaining[(i + m - 1) % m];
+            let b = remaining[i];
+            let c = remaining[(i + 1) % m];
+            cross(ps[a], ps[b], ps[c]) > 1e-12f64 &&
+                !remaining.iter()
+                    .filter(|&&j| j != a && j != b && j != c)
+                    .any(|&j| in_triangle(ps[j], ps[a], ps[b], ps[c]))
+        });
+        match ear {
+            Some(i) => {
+                triangles.push([remaining[(i + m - 1) % m], remaining[i], remaining[(i + 1) % m]]);
+                remaining.remove(i);
+            }
+            None => {
+                // 自己交差などで耳が見つからない場合は扇形に分割
+                for i in 1..(m - 1) {
+                    triangles.push([remaining[0], remaining[i], remaining[i + 1]]);
+                }
+                return triangles;
+            }
+        }
+    }
+    triangles.push([remaining[0], remaining[1], remaining[2]]);
+    triangles
+}
+
+// 扇形に分割
+fn fan(n: usize) -> Vec<[usize; 3]> {
+    (1..(n - 1)).map(|i| [0, i, i + 1]).collect()
+}
+
+fn cross(a: (f64, f64), b: (f64, f64), c: (f64, f64)) -> f64 {
+    (b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0)
+}
+
+fn in_triangle(p: (f64, f64), a: (f64, f64), b: (f64, f64), c: (f64, f64)) -> bool {
+    cross(a, b, p) >= 0.0f64 && cross(b, c, p) >= 0.0f64 && cross(c, a, p) >= 0.0f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Result<ObjModel, LoadError> {
+        ObjModel::parse(s.as_bytes())
+    }
+
+    #[test]
+    fn face_forms() {
+        {
+            let model = parse("v 0 0 0\nv 1 0 0\nv 1 1 0\nvt 0 0\nvt 1 0\nvt 1 1\nvn 0 0 1\n\
+                               f 1 2 3\nf 1/1 2/2 3/3\nf 1//1 2//1 3//1\nf 1/1/1 2/2/1 3/3/1\n").unwrap();
+            assert_eq!(0, model.skipped.len());
+            assert_eq!(4, model.faces.len());
+            assert_eq!(FaceIndex { v: 1, vt: None, vn: None }, model.faces[0].indices[1]);
+            assert_eq!(FaceIndex { v: 1, vt: Some(1), vn: None }, model.faces[1].indices[1]);
+            assert_eq!(FaceIndex { v: 1, vt: None, vn: Some(0) }, model.faces[2].indices[1]);
+            assert_eq!(FaceIndex { v: 1, vt: Some(1), vn: Some(0) }, model.faces[3].indices[1]);
+        }
+    }
+
+    #[test]
+    fn materials() {
+        {
+            let model = parse("mtllib a.mtl\nv 0 0 0\nv 1 0 0\nv 1 1 0\nf 1 2 3\n\
+                               usemtl skin\nf 1 2 3\nusemtl hair 01\nf 1 2 3\nusemtl skin\nf 1 2 3\n").unwrap();
+            assert_eq!(vec!["a.mtl".to_string()], model.material_libs);
+            assert_eq!(vec!["skin".to_string(), "hair 01".to_string()], model.materials);
+            let ms: Vec<Option<usize>> = model.faces.iter().map(|f| f.material).collect();
+            assert_eq!(vec![None, Some(0), Some(1), Some(0)], ms);
+        }
+    }
+
+    #[test]
+    fn vertex_weight() {
+        {
+            // 重みは同次座標のwにしない
+            let model = parse("v 1 2 3 0.5\nv 1 0 0 0\nv 1 1 0\nf 1 2 3\n").unwrap();
+            assert_eq!(Point3::new(1f64, 2f64, 3f64, 1f64), model.vertices[0]);
+            assert_eq!(1f64, model.vertices[1].w);
+            // 頂点色は使わない
+            let model = parse("v 1 2 3 1 0 0\nv 1 0 0 1 0 1 0\nv 1 1 0\nf 1 2 3\n").unwrap();
+            assert_eq!(Point3::new(1f64, 2f64, 3f64, 1f64), model.vertices[0]);
+            assert_eq!(Point3::new(1f64, 0f64, 0f64, 1f64), model.vertices[1]);
+            assert!(parse("v 1 2 3 1 0\nv 1 0 0\nv 1 1 0\nf 1 2 3\n").is_err());
+        }
+    }
+
+    #[test]
+    fn missing_tex_coord() {
+        {
+            // vtのない添字はないものとして警告を残す
+            let model = parse("v 0 0 0\nv 1 0 0\nv 1 1 0\nf 1/1 2/1 3/1\nvt 0 0\nf 1/1 2/2 3/1\n").unwrap();
+            assert_eq!(FaceIndex { v: 0, vt: None, vn: None }, model.faces[0].indices[0]);
+            assert_eq!(vec![Some(0), None, Some(0)], model.faces[1].indices.iter().map(|i| i.vt).collect::<Vec<_>>());
+            assert_eq!(vec![4, 6], model.skipped.iter().map(|s| s.line).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn bundled_models() {
+        {
+            // 同梱のモデルは全て読める
+            for name in ["box.obj", "human.obj", "miku.obj", "teapot.obj"].iter() {
+                let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(name);
+                let model = ObjModel::load(&path).unwrap_or_else(|e| panic!("{}: {}", name, e));
+                assert!(!model.triangles().is_empty(), "{}", name);
+            }
+        }
+    }
+
+    #[test]
+    fn negative_index() {
+        {
+            let model = parse("v 0 0 0\nv 1 0 0\nv 1 1 0\nf -3 -2 -1\n").unwrap();
+            assert_eq!(0, model.skipped.len());
+            let vs: Vec<usize> = model.faces[0].indices.iter().map(|i| i.v).collect();
+            assert_eq!(vec![0, 1, 2], vs);
+        }
+    }
+
+    #[test]
+    fn skipped_lines() {
+        {
+            let model = parse("v 0 0 0\nv 1 0 0\nv 1 1 0\nfoo bar\n# comment\ng group\nf 1 2 3\nl 1 2\np 3\n").unwrap();
+            let lines: Vec<usize> = model.skipped.iter().map(|s| s.line).collect();
+            // 線と点は描画しないので読み飛ばしたことを残す
+            assert_eq!(vec![4, 8, 9], lines);
+            assert_eq!(1, model.faces.len());
+        }
+    }
+
+    #[test]
+    fn errors() {
+        {
+            match parse("v 0 0 0\nv 1 x 2\n") {
+                Err(LoadError::Parse { line: 2, column: 5, .. }) => (),
+                r => panic!("{:?}", r)
+            }
+            match parse("v 0 0 0\nv 1 0 0\nv 1 1 0\nf 1 2 4\n") {
+                Err(LoadError::IndexOutOfRange { line: 4, column: 7, index: 4, count: 3 }) => (),
+                r => panic!("{:?}", r)
+            }
+            match parse("v 0 0 0\nvt 0 0\nf 1/1 1/x 1/1\n") {
+                Err(LoadError::Parse { line: 3, column: 9, .. }) => (),
+                r => panic!("{:?}", r)
+            }
+            match parse("v 0 0 0\n") {
+                Err(LoadError::EmptyMesh) => (),
+                r => panic!("{:?}", r)
+            }
+        }
+    }
+
+    #[test]
+    fn triangulate_ngon() {
+        {
+            // 凹んだ5角形
+            let points = vec![
+                Point3::new(0.0f64, 0.0f64, 0.0f64, 1.0f64),
+                Point3::new(2.0f64, 0.0f64, 0.0f64, 1.0f64),
+                Point3::new(2.0f64, 2.0f64, 0.0f64, 1.0f64),
+                Point3::new(1.0f64, 0.5f64, 0.0f64, 1.0f64),
+                Point3::new(0.0f64, 2.0f64, 0.0f64, 1.0f64)];
+            let triangles = triangulate(&points);
+            assert_eq!(3, triangles.len());
+            // 分割した三角形の面積の合計が元の多角形と一致する
+            let area: f64 = triangles.iter()
+                .map(|t| {
+                    let a = (points[t[0]].x, points[t[0]].y);
+                    let b = (points[t[1]].x, points[t[1]].y);
+                    let c = (points[t[2]].x, points[t[2]].y);
+                    cross(a, b, c) / 2.0f64
+                })
+                .sum();
+            assert!((area - 2.5f64).abs() < 1e-10f64);
+        }
+    }
+}
@@ -1,7 +1,8 @@
 use std::ops::{Add, Sub, Mul, Div};
 use util::vector3::Vector3;
+use util::matrix4::Matrix4;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Point3 {
     pub x: f64,
     pub y: f64,
@@ -13,6 +14,15 @@ impl Point3 {
     pub fn new(x: f64, y: f64, z: f64, w: f64) -> Point3 {
         Point3 { x, y, z, w }
     }
+    // アフィン変換
+    pub fn affin(&self, mat: &Matrix4) -> Point3 {
+        Point3::from(mat * &Vector3::from(*self))
+    }
+    // 各要素の差がeps以下か
+    pub fn approx_eq(&self, rhs: &Point3, eps: f64) -> bool {
+        (self.x - rhs.x).abs() <= eps && (self.y - rhs.y).abs() <= eps &&
+            (self.z - rhs.z).abs() <= eps && (self.w - rhs.w).abs() <= eps
+    }
 }
 
 // Vector3<T>からPoint3<T>への変換
@@ -35,7 +45,7 @@ impl<'a> Add<&'a Point3> for &'a Point3 {
     type Output = Point3;
 
     // 座標同士の加算
-    fn add<'b>(self, rhs: &'b Point3) -> Point3 {
+    fn add(self, rhs: &Point3) -> Point3 {
         Point3::new(self.x + rhs.x, self.y + rhs.y, self.z + rhs.z, 1.0f64)
     }
 }
@@ -44,7 +54,7 @@ impl<'a> Sub<&'a Point3> for &'a Point3 {
     type Output = Point3;
 
     // 座標同士の減算
-    fn sub<'b>(self, rhs: &'b Point3) ->Point3 {
+    fn sub(self, rhs: &Point3) ->Point3 {
         Point3::new(self.x - rhs.x, self.y - rhs.y, self.z - rhs.z, 1.0f64)
     }
 }
@@ -53,7 +63,7 @@ impl<'a> Add<&'a Vector3> for &'a Point3 {
     type Output = Point3;
 
     // ベクトルとの加算
-    fn add<'b>(self, rhs: &'b Vector3) -> Point3 {
+    fn add(self, rhs: &Vector3) -> Point3 {
         Point3::new(self.x + rhs.x, self.y + rhs.y, self.z + rhs.z, 1.0f64)
     }
 }
@@ -62,12 +72,12 @@ impl<'a> Sub<&'a Vector3> for &'a Point3 {
     type Output = Point3;
 
     // ベクトルとの減算
-    fn sub<'b>(self, rhs: &'b Vector3) -> Point3 {
+    fn sub(self, rhs: &Vector3) -> Point3 {
         Point3::new(self.x - rhs.x, self.y - rhs.y, self.z - rhs.z, 1.0f64)
     }
 }
 
-impl<'a> Mul<f64> for &'a Point3 {
+impl Mul<f64> for &Point3 {
     type Output = Point3;
 
     // スカラ値の乗算
@@ -76,7 +86,7 @@ impl<'a> Mul<f64> for &'a Point3 {
     }
 }
 
-impl<'a> Div<f64> for &'a Point3 {
+impl Div<f64> for &Point3 {
     type Output = Point3;
 
     // スカラ値の除算
@@ -92,31 +102,87 @@ mod tests {
     #[test]
     fn add1() {
         {
-            let a = Point3::new(1, 2, 3, 1);
-            let b = Vector3::new(2, 3, 4, 1);
-            let c = a + b;
-            assert_eq!(Point3::new(3, 5, 7, 1), c);
+            let a = Point3::new(1f64, 2f64, 3f64, 1f64);
+            let b = Vector3::new(2f64, 3f64, 4f64, 1f64);
+            let c = &a + &b;
+            assert_eq!(Point3::new(3f64, 5f64, 7f64, 1f64), c);
+        }
+    }
+
+    #[test]
+    fn add2() {
+        {
+            let a = Point3::new(1f64, 2f64, 3f64, 1f64);
+            let b = Point3::new(2f64, 3f64, 4f64, 1f64);
+            let c = &a + &b;
+            assert_eq!(Point3::new(3f64, 5f64, 7f64, 1f64), c);
         }
     }
 
     #[test]
     fn sub1() {
         {
-            let a = Point3::new(1, 2, 3, 1);
-            let b = Vector3::new(2, 3, 4, 1);
-            let c = a - b;
-            assert_eq!(Point3::new(-1, -1, -1, 1), c);
+            let a = Point3::new(1f64, 2f64, 3f64, 1f64);
+            let b = Vector3::new(2f64, 3f64, 4f64, 1f64);
+            let c = &a - &b;
+            assert_eq!(Point3::new(-1f64, -1f64, -1f64, 1f64), c);
         }
     }
 
     #[test]
     fn sub2() {
         {
-            let a = Point3::new(1, 2, 3, 1);
-            let b = Point3::new(2, 3, 4, 1);
-            let c = a - b;
-            assert_eq!(Point3::new(-1, -1, -1, 1), c);
+            let a = Point3::new(1f64, 2f64, 3f64, 1f64);
+            let b = Point3::new(2f64, 3f64, 4f64, 1f64);
+            let c = &a - &b;
+            assert_eq!(Point3::new(-1f64, -1f64, -1f64, 1f64), c);
+        }
+    }
+
+    #[test]
+    fn mul() {
+        {
+            // スカラ値の乗算
+            let a = Point3::new(1f64, 2f64, 3f64, 1f64);
+            assert_eq!(Point3::new(2f64, 4f64, 6f64, 1f64), &a * 2f64);
+        }
+    }
+
+    #[test]
+    fn div() {
+        {
+            // スカラ値の除算
+            let a = Point3::new(2f64, 4f64, 6f64, 2f64);
+            assert_eq!(Point3::new(1f64, 2f64, 3f64, 1f64), &a / 2f64);
+        }
+    }
+
+    #[test]
+    fn affin() {
+        {
+            // アフィン変換(移動してから回転)
+            let a = Point3::new(1f64, 0f64, 0f64, 1f64);
+            let m = &Matrix4::rotation_z(::std::f64::consts::PI / 2f64) *
+                &Matrix4::translation(&Vector3::new(1f64, 0f64, 0f64, 0f64));
+            let b = a.affin(&m);
+            assert!(Point3::new(0f64, 2f64, 0f64, 1f64).approx_eq(&b, 1e-10f64), "{:?}", b);
+        }
+    }
+
+    #[test]
+    fn from() {
+        {
+            let a = Vector3::new(1f64, 2f64, 3f64, 4f64);
+            assert_eq!(Point3::new(1f64, 2f64, 3f64, 4f64), Point3::from(a));
         }
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn approx_eq() {
+        {
+            let a = Point3::new(1f64, 2f64, 3f64, 1f64);
+            assert!(a.approx_eq(&Point3::new(1f64 + 1e-12f64, 2f64, 3f64, 1f64), 1e-10f64));
+            assert!(!a.approx_eq(&Point3::new(1f64, 2f64, 3.1f64, 1f64), 1e-10f64));
+        }
+    }
+}
@@ -0,0 +1,233 @@
+use std::ops::Mul;
+use util::vector3::Vector3;
+use util::matrix4::Matrix4;
+
+// 回転を表す四元数(w + xi + yj + zk)
+#[derive(Debug, Copy, Clone)]
+pub struct Quaternion {
+    pub w: f64,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64
+}
+
+impl Quaternion {
+    pub fn new(w: f64, x: f64, y: f64, z: f64) -> Quaternion {
+        Quaternion { w, x, y, z }
+    }
+    // 回転なし
+    pub fn identity() -> Quaternion {
+        Quaternion::new(1.0f64, 0.0f64, 0.0f64, 0.0f64)
+    }
+    // 軸周りにr回転
+    pub fn from_axis_angle(axis: &Vector3, r: f64) -> Quaternion {
+        let a = axis.normalize();
+        let s = (r / 2.0f64).sin();
+        Quaternion::new((r / 2.0f64).cos(), a.x * s, a.y * s, a.z * s)
+    }
+    // X軸、Y軸、Z軸の順に回転(Matrix4::rotation_z * rotation_y * rotation_xと同じ)
+    pub fn from_euler(x: f64, y: f64, z: f64) -> Quaternion {
+        let qx = Quaternion::from_axis_angle(&Vector3::new(1.0f64, 0.0f64, 0.0f64, 0.0f64), x);
+        let qy = Quaternion::from_axis_angle(&Vector3::new(0.0f64, 1.0f64, 0.0f64, 0.0f64), y);
+        let qz = Quaternion::from_axis_angle(&Vector3::new(0.0f64, 0.0f64, 1.0f64, 0.0f64), z);
+        &(&qz * &qy) * &qx
+    }
+    // 回転行列から作成(左上の3x3だけを見る)
+    pub fn from_matrix(mat: &Matrix4) -> Quaternion {
+        let m = |y: usize, x: usize| mat.xs[y * 4 + x];
+        let trace = m(0, 0) + m(1, 1) + m(2, 2);
+        let q = if trace > 0.0f64 {
+            let s = (trace + 1.0f64).sqrt() * 2.0f64;
+            Quaternion::new(s / 4.0f64, (m(2, 1) - m(1, 2)) / s, (m(0, 2) - m(2, 0)) / s, (m(1, 0) - m(0, 1)) / s)
+        } else if m(0, 0) > m(1, 1) && m(0, 0) > m(2, 2) {
+            let s = (1.0f64 + m(0, 0) - m(1, 1) - m(2, 2)).sqrt() * 2.0f64;
+            Quaternion::new((m(2, 1) - m(1, 2)) / s, s / 4.0f64, (m(0, 1) + m(1, 0)) / s, (m(0, 2) + m(2, 0)) / s)
+        } else if m(1, 1) > m(2, 2) {
+            let s = (1.0f64 + m(1, 1) - m(0, 0) - m(2, 2)).sqrt() * 2.0f64;
+            Quaternion::new((m(0, 2) - m(2, 0)) / s, (m(0, 1) + m(1, 0)) / s, s / 4.0f64, (m(1, 2) + m(2, 1)) / s)
+        } else {
+            let s = (1.0f64 + m(2, 2) - m(0, 0) - m(1, 1)).sqrt() * 2.0f64;
+            Quaternion::new((m(1, 0) - m(0, 1)) / s, (m(0, 2) + m(2, 0)) / s, (m(1, 2) + m(2, 1)) / s, s / 4.0f64)
+        };
+        q.normalize()
+    }
+    // 回転軸と角度
+    pub fn to_axis_angle(self) -> (Vector3, f64) {
+        let q = self.normalize();
+        let w = q.w.clamp(-1.0f64, 1.0f64);
+        let s = (1.0f64 - w * w).sqrt();
+        if s < 1e-10f64 {
+            // 回転なしなら軸はどれでもよい
+            return (Vector3::new(1.0f64, 0.0f64, 0.0f64, 0.0f64), 0.0f64);
+        }
+        (Vector3::new(q.x / s, q.y / s, q.z / s, 0.0f64), 2.0f64 * w.acos())
+    }
+    // 回転行列
+    // | 1 - 2(y y + z z) | 2(x y - w z)     | 2(x z + w y)     | 0 |
+    // | 2(x y + w z)     | 1 - 2(x x + z z) | 2(y z - w x)     | 0 |
+    // | 2(x z - w y)     | 2(y z + w x)     | 1 - 2(x x + y y) | 0 |
+    // | 0                | 0                | 0                | 1 |
+    pub fn to_matrix(self) -> Matrix4 {
+        let q = self.normalize();
+        let (w, x, y, z) = (q.w, q.x, q.y, q.z);
+        Matrix4::new(
+            [1.0f64 - 2.0f64 * (y * y + z * z), 2.0f64 * (x * y - w * z), 2.0f64 * (x * z + w * y), 0.0f64,
+                2.0f64 * (x * y + w * z), 1.0f64 - 2.0f64 * (x * x + z * z), 2.0f64 * (y * z - w * x), 0.0f64,
+                2.0f64 * (x * z - w * y), 2.0f64 * (y * z + w * x), 1.0f64 - 2.0f64 * (x * x + y * y), 0.0f64,
+                0.0f64, 0.0f64, 0.0f64, 1.0f64]
+        )
+    }
+    // 内積
+    pub fn dot(&self, rhs: &Quaternion) -> f64 {
+        self.w * rhs.w + self.x * rhs.x + self.y * rhs.y + self.z * rhs.z
+    }
+    // 大きさ
+    pub fn norm(&self) -> f64 {
+        self.dot(self).sqrt()
+    }
+    // 大きさを1にする
+    pub fn normalize(&self) -> Quaternion {
+        let n = self.norm();
+        Quaternion::new(self.w / n, self.x / n, self.y / n, self.z / n)
+    }
+    // 共役(単位四元数なら逆回転)
+    pub fn conjugate(&self) -> Quaternion {
+        Quaternion::new(self.w, -self.x, -self.y, -self.z)
+    }
+    // ベクトルを回転
+    pub fn rotate(&self, v: &Vector3) -> Vector3 {
+        let p = Quaternion::new(0.0f64, v.x, v.y, v.z);
+        let q = self.normalize();
+        let r = &(&q * &p) * &q.conjugate();
+        Vector3::new(r.x, r.y, r.z, v.w)
+    }
+    // selfからotherへ球面線形補間(tは0〜1)
+    pub fn slerp(&self, other: &Quaternion, t: f64) -> Quaternion {
+        let a = self.normalize();
+        let mut b = other.normalize();
+        let mut d = a.dot(&b);
+        // 近い方の回り方を選ぶ
+        if d < 0.0f64 {
+            b = Quaternion::new(-b.w, -b.x, -b.y, -b.z);
+            d = -d;
+        }
+        let (s1, s2) = if d > 0.9995f64 {
+            // ほとんど同じ向きなら線形補間で十分
+            (1.0f64 - t, t)
+        } else {
+            let theta = d.acos();
+            let s = theta.sin();
+            (((1.0f64 - t) * theta).sin() / s, (t * theta).sin() / s)
+        };
+        Quaternion::new(
+            a.w * s1 + b.w * s2,
+            a.x * s1 + b.x * s2,
+            a.y * s1 + b.y * s2,
+            a.z * s1 + b.z * s2).normalize()
+    }
+}
+
+impl<'a> Mul<&'a Quaternion> for &'a Quaternion {
+    type Output = Quaternion;
+
+    // 合成(rhsの回転のあとにselfの回転)
+    fn mul(self, rhs: &Quaternion) -> Quaternion {
+        Quaternion::new(
+            self.w * rhs.w - self.x * rhs.x - self.y * rhs.y - self.z * rhs.z,
+            self.w * rhs.x + self.x * rhs.w + self.y * rhs.z - self.z * rhs.y,
+            self.w * rhs.y - self.x * rhs.z + self.y * rhs.w + self.z * rhs.x,
+            self.w * rhs.z + self.x * rhs.y - self.y * rhs.x + self.z * rhs.w)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::PI;
+
+    fn assert_matrix(expected: &Matrix4, actual: &Matrix4) {
+        assert!(expected.approx_eq(actual, 1e-10f64), "expected {:?}, actual {:?}", expected, actual);
+    }
+
+    fn assert_vector(expected: &Vector3, actual: &Vector3) {
+        assert!(expected.approx_eq(actual, 1e-10f64), "expected {:?}, actual {:?}", expected, actual);
+    }
+
+    #[test]
+    fn axis_angle() {
+        {
+            let axis = Vector3::new(1f64, 2f64, 3f64, 0f64);
+            let q = Quaternion::from_axis_angle(&axis, 0.7f64);
+            assert_matrix(&Matrix4::rotation_axis_angle(&axis, 0.7f64), &q.to_matrix());
+            let (a, r) = q.to_axis_angle();
+            let n = 14f64.sqrt();
+            assert_vector(&Vector3::new(1f64 / n, 2f64 / n, 3f64 / n, 0f64), &a);
+            assert!((r - 0.7f64).abs() < 1e-10f64);
+            // Z軸周りに90度
+            let z = Quaternion::from_axis_angle(&Vector3::new(0f64, 0f64, 1f64, 0f64), PI / 2f64);
+            assert_vector(&Vector3::new(0f64, 1f64, 0f64, 0f64), &z.rotate(&Vector3::new(1f64, 0f64, 0f64, 0f64)));
+        }
+    }
+
+    #[test]
+    fn euler() {
+        {
+            let q = Quaternion::from_euler(0.1f64, 0.2f64, 0.3f64);
+            let m = &(&Matrix4::rotation_z(0.3f64) * &Matrix4::rotation_y(0.2f64)) * &Matrix4::rotation_x(0.1f64);
+            assert_matrix(&m, &q.to_matrix());
+        }
+    }
+
+    #[test]
+    fn matrix() {
+        {
+            // 行列から戻すと同じ回転になる(trの正負で場合分けされる全ての経路)
+            for &(x, y, z) in [(0.1f64, 0.2f64, 0.3f64), (PI, 0f64, 0f64), (0f64, PI, 0f64),
+                               (0f64, 0f64, PI), (2.5f64, -1.0f64, 3.0f64)].iter() {
+                let m = Quaternion::from_euler(x, y, z).to_matrix();
+                assert_matrix(&m, &Quaternion::from_matrix(&m).to_matrix());
+            }
+        }
+    }
+
+    #[test]
+    fn mul() {
+        {
+            // 合成は行列の積と同じ
+            let a = Quaternion::from_axis_angle(&Vector3::new(0f64, 1f64, 0f64, 0f64), 0.5f64);
+            let b = Quaternion::from_axis_angle(&Vector3::new(1f64, 1f64, 0f64, 0f64), 1.2f64);
+            assert_matrix(&(&a.to_matrix() * &b.to_matrix()), &(&a * &b).to_matrix());
+            // 逆回転を合成すると回転なし
+            assert_matrix(&Matrix4::identity(), &(&a * &a.conjugate()).to_matrix());
+            assert_matrix(&Matrix4::identity(), &Quaternion::identity().to_matrix());
+        }
+    }
+
+    #[test]
+    fn normalize() {
+        {
+            let q = Quaternion::new(1f64, 2f64, 3f64, 4f64).normalize();
+            assert!((q.norm() - 1f64).abs() < 1e-10f64);
+            assert!((q.y / q.x - 1.5f64).abs() < 1e-10f64);
+        }
+    }
+
+    #[test]
+    fn slerp() {
+        {
+            let y = Vector3::new(0f64, 1f64, 0f64, 0f64);
+            let a = Quaternion::from_axis_angle(&y, 0.2f64);
+            let b = Quaternion::from_axis_angle(&y, 1.4f64);
+            // 両端
+            assert_matrix(&a.to_matrix(), &a.slerp(&b, 0f64).to_matrix());
+            assert_matrix(&b.to_matrix(), &a.slerp(&b, 1f64).to_matrix());
+            // 角度が一定の速さで変わる
+            let (_, r) = a.slerp(&b, 0.25f64).to_axis_angle();
+            assert!((r - 0.5f64).abs() < 1e-10f64);
+            // 符号が逆でも同じ回転なので近い方を回る
+            let c = Quaternion::new(-b.w, -b.x, -b.y, -b.z);
+            let (_, r) = a.slerp(&c, 0.5f64).to_axis_angle();
+            assert!((r - 0.8f64).abs() < 1e-10f64);
+        }
+    }
+}
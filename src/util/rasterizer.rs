@@ -0,0 +1,148 @@
+use util::color::Color;
+use util::point3::Point3;
+use util::framebuffer::FrameBuffer;
+
+// 辺abに対する点pの位置(符号付き面積の2倍)
+fn edge(a: &Point3, b: &Point3, px: f64, py: f64) -> f64 {
+    (b.x - a.x) * (py - a.y) - (b.y - a.y) * (px - a.x)
+}
+
+// 三角形を塗りつぶす
+// positionsはスクリーン座標(zは深度で小さいほど手前)、wsは透視補正に使う射影後のw
+// 深度テストに通った画素だけshaderに透視補正した重心座標を渡し、画素の色を返してもらう
+// blendなら半透明の面として深度は書き込まず、返した色を不透明度に従って今の色と混ぜる
+pub fn fill_triangle<F>(fb: &mut FrameBuffer, positions: &[Point3; 3], ws: &[f64; 3], blend: bool, mut shader: F)
+    where F: FnMut(&[f64; 3]) -> Color {
+    let (p0, p1, p2) = (&positions[0], &positions[1], &positions[2]);
+    let area = edge(p0, p1, p2.x, p2.y);
+    if area.abs() < 1e-12f64 || fb.width == 0 || fb.height == 0 {
+        return;
+    }
+    // 画面内の外接矩形だけを走査する
+    let min_x = p0.x.min(p1.x).min(p2.x).floor().max(0.0f64) as usize;
+    let min_y = p0.y.min(p1.y).min(p2.y).floor().max(0.0f64) as usize;
+    let max_x = p0.x.max(p1.x).max(p2.x).ceil().min(fb.width as f64 - 1.0f64);
+    let max_y = p0.y.max(p1.y).max(p2.y).ceil().min(fb.height as f64 - 1.0f64);
+    if max_x < 0.0f64 || max_y < 0.0f64 {
+        return;
+    }
+    let (max_x, max_y) = (max_x as usize, max_y as usize);
+    for y in min_y..(max_y + 1) {
+        // 画素の中心で判定する
+        let py = y as f64 + 0.5f64;
+        for x in min_x..(max_x + 1) {
+            let px = x as f64 + 0.5f64;
+            // 重心座標(向きによらず内側が正)
+            let b0 = edge(p1, p2, px, py) / area;
+            let b1 = edge(p2, p0, px, py) / area;
+            let b2 = edge(p0, p1, px, py) / area;
+            if b0 < 0.0f64 || b1 < 0.0f64 || b2 < 0.0f64 {
+                continue;
+            }
+            // 深度は画面上で線形に補間できる
+            let z = p0.z * b0 + p1.z * b1 + p2.z * b2;
+            let visible = if blend { fb.test_depth(x, y, z) } else { fb.test_and_set_depth(x, y, z) };
+            if !visible {
+                continue;
+            }
+            // 透視補正(1/wで重み付けする)
+            let q0 = b0 / ws[0];
+            let q1 = b1 / ws[1];
+            let q2 = b2 / ws[2];
+            let q = q0 + q1 + q2;
+            let color = shader(&[q0 / q, q1 / q, q2 / q]);
+            if blend {
+                fb.blend(x, y, &color);
+            } else {
+                fb.set(x, y, &color);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fill() {
+        {
+            let mut fb = FrameBuffer::new(4, 4);
+            let white = Color::new(255, 255, 255);
+            let positions = [
+                Point3::new(0.0f64, 0.0f64, 0.0f64, 1.0f64),
+                Point3::new(4.0f64, 0.0f64, 0.0f64, 1.0f64),
+                Point3::new(0.0f64, 4.0f64, 0.0f64, 1.0f64)];
+            fill_triangle(&mut fb, &positions, &[1.0f64, 1.0f64, 1.0f64], false, |_| white);
+            // 対角線より左上だけ塗られる
+            assert_eq!(255, fb.get(0, 0).r);
+            assert_eq!(255, fb.get(2, 0).r);
+            assert_eq!(255, fb.get(1, 1).r);
+            assert_eq!(0, fb.get(3, 3).r);
+            assert_eq!(0, fb.get(2, 2).r);
+        }
+    }
+
+    #[test]
+    fn depth() {
+        {
+            let mut fb = FrameBuffer::new(4, 4);
+            let red = Color::new(255, 0, 0);
+            let blue = Color::new(0, 0, 255);
+            let square = |z: f64| [
+                Point3::new(0.0f64, 0.0f64, z, 1.0f64),
+                Point3::new(4.0f64, 0.0f64, z, 1.0f64),
+                Point3::new(0.0f64, 4.0f64, z, 1.0f64)];
+            // 手前の三角形を先に描いても奥の三角形で上書きされない
+            fill_triangle(&mut fb, &square(1.0f64), &[1.0f64, 1.0f64, 1.0f64], false, |_| red);
+            fill_triangle(&mut fb, &square(2.0f64), &[1.0f64, 1.0f64, 1.0f64], false, |_| blue);
+            assert_eq!(255, fb.get(0, 0).r);
+            assert_eq!(1.0f64, fb.depth[0]);
+            // より手前なら上書きされる
+            fill_triangle(&mut fb, &square(0.5f64), &[1.0f64, 1.0f64, 1.0f64], false, |_| blue);
+            assert_eq!(255, fb.get(0, 0).b);
+        }
+    }
+
+    #[test]
+    fn blend() {
+        {
+            let mut fb = FrameBuffer::new(4, 4);
+            let square = |z: f64| [
+                Point3::new(0.0f64, 0.0f64, z, 1.0f64),
+                Point3::new(4.0f64, 0.0f64, z, 1.0f64),
+                Point3::new(0.0f64, 4.0f64, z, 1.0f64)];
+            fill_triangle(&mut fb, &square(1.0f64), &[1.0f64, 1.0f64, 1.0f64], false, |_| Color::new(255, 0, 0));
+            // 手前の半透明な面は混ざるが深度は書き換えない
+            fill_triangle(&mut fb, &square(0.5f64), &[1.0f64, 1.0f64, 1.0f64], true, |_| Color::rgba(0, 0, 255, 128));
+            assert!(Color::new(188, 0, 188).approx_eq(&fb.get(0, 0), 1), "{:?}", fb.get(0, 0));
+            assert_eq!(1.0f64, fb.depth[0]);
+            // 奥の半透明な面は隠れる
+            fill_triangle(&mut fb, &square(2.0f64), &[1.0f64, 1.0f64, 1.0f64], true, |_| Color::rgba(0, 255, 0, 128));
+            assert_eq!(0, fb.get(0, 0).g);
+        }
+    }
+
+    #[test]
+    fn perspective_correct() {
+        {
+            let mut fb = FrameBuffer::new(4, 4);
+            let positions = [
+                Point3::new(0.0f64, 0.0f64, 0.0f64, 1.0f64),
+                Point3::new(4.0f64, 0.0f64, 0.0f64, 1.0f64),
+                Point3::new(0.0f64, 4.0f64, 0.0f64, 1.0f64)];
+            let mut weights = Vec::new();
+            // 奥の頂点(w=3)の影響は画面上の面積より小さくなる
+            fill_triangle(&mut fb, &positions, &[1.0f64, 3.0f64, 1.0f64], false, |b| {
+                weights.push(*b);
+                Color::new(0, 0, 0)
+            });
+            for b in weights.iter() {
+                assert!((b[0] + b[1] + b[2] - 1.0f64).abs() < 1e-10f64);
+            }
+            // (1.5, 0.5)の画面上の重みは0.375
+            let b = weights[1];
+            assert!((b[1] - 0.375f64 / 3.0f64 / (0.5f64 + 0.375f64 / 3.0f64 + 0.125f64)).abs() < 1e-10f64);
+        }
+    }
+}
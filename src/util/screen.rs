@@ -1,32 +1,39 @@
 use util::size2::Size2;
 use util::point3::Point3;
-use util::polygon3::Polygon3;
 
 #[derive(Debug)]
 pub struct Screen {
-    pub size: Size2,
-    pub scale: f64
+    pub size: Size2
 }
 
 impl Screen {
-    pub fn new(size: &Size2, scale: f64) -> Screen {
-        Screen { size: *size, scale }
+    pub fn new(size: &Size2) -> Screen {
+        Screen { size: *size }
     }
-    // 投影面をディスプレイに合わせる
-    fn _convert_to_screen(&self, point : &Point3) -> Point3 {
+    // 投影面(-1〜1)をディスプレイに合わせる
+    pub fn convert_point_to_screen(&self, point : &Point3) -> Point3 {
         Point3 {
-            x: self.size.width / 2.0f64 + point.x * self.scale,
-            y: self.size.height / 2.0f64 - point.y * self.scale,
+            x: self.size.width / 2.0f64 * (1.0f64 + point.x),
+            y: self.size.height / 2.0f64 * (1.0f64 - point.y),
             z: point.z,
             w: 1.0f64
         }
     }
-    // ポリゴンをスクリーン(画面)の座標に合わせる
-    pub fn convert_to_screen(&self, polygon : &Polygon3) -> Polygon3 {
-        Polygon3 {
-            p1: self._convert_to_screen(&polygon.p1),
-            p2: self._convert_to_screen(&polygon.p2),
-            p3: self._convert_to_screen(&polygon.p3)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn convert_point_to_screen() {
+        {
+            // 投影面の-1〜1が画面の端から端になり、yは下向き
+            let s = Screen::new(&Size2::new(640f64, 480f64));
+            let p = |x: f64, y: f64| s.convert_point_to_screen(&Point3::new(x, y, 0.5f64, 1f64));
+            assert_eq!(Point3::new(320f64, 240f64, 0.5f64, 1f64), p(0f64, 0f64));
+            assert_eq!(Point3::new(0f64, 0f64, 0.5f64, 1f64), p(-1f64, 1f64));
+            assert_eq!(Point3::new(640f64, 480f64, 0.5f64, 1f64), p(1f64, -1f64));
         }
     }
 }
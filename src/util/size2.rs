@@ -6,6 +6,6 @@ pub struct Size2 {
 
 impl Size2 {
     pub fn new(width: f64, height: f64) -> Size2 {
-        Size2 { width: width, height: height }
+        Size2 { width, height }
     }
 }
\ No newline at end of file
@@ -0,0 +1,11 @@
+#[derive(Debug, Copy, Clone)]
+pub struct TexCoord {
+    pub u: f64,
+    pub v: f64
+}
+
+impl TexCoord {
+    pub fn new(u: f64, v: f64) -> TexCoord {
+        TexCoord { u, v }
+    }
+}
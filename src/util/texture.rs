@@ -0,0 +1,149 @@
+use std::path::Path;
+
+use gdk_pixbuf::Pixbuf;
+
+use util::color::Color;
+use util::linear_color::LinearColor;
+use util::load_error::LoadError;
+use util::tex_coord::TexCoord;
+
+// 画素の補間方法
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Filter {
+    // 最近傍
+    Nearest,
+    // 双線形補間
+    Bilinear
+}
+
+// 範囲外のテクスチャ座標の扱い
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Wrap {
+    // 繰り返し
+    Repeat,
+    // 端の画素を延ばす
+    Clamp
+}
+
+// テクスチャ画像(画素は読み込み時に線形な値にしておく)
+#[derive(Debug, Clone)]
+pub struct Texture {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<LinearColor>,
+    pub filter: Filter,
+    pub wrap: Wrap
+}
+
+impl Texture {
+    pub fn new(width: usize, height: usize, pixels: Vec<Color>) -> Texture {
+        assert_eq!(width * height, pixels.len());
+        let pixels = pixels.iter().map(LinearColor::from_srgb).collect();
+        Texture { width, height, pixels, filter: Filter::Bilinear, wrap: Wrap::Repeat }
+    }
+
+    // 画像ファイル(PNG、JPEGなど)から読み込み
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Texture, LoadError> {
+        let path = path.as_ref();
+        let pixbuf = Pixbuf::new_from_file(&path.to_string_lossy())
+            .map_err(|e| LoadError::Image(format!("{}: {}", path.display(), e)))?;
+        let width = pixbuf.get_width() as usize;
+        let height = pixbuf.get_height() as usize;
+        let channels = pixbuf.get_n_channels() as usize;
+        let stride = pixbuf.get_rowstride() as usize;
+        if pixbuf.get_bits_per_sample() != 8 || channels < 3 {
+            return Err(LoadError::Image(format!("{}: unsupported pixel format", path.display())));
+        }
+        let data = unsafe { pixbuf.get_pixels() };
+        let mut pixels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                let i = y * stride + x * channels;
+                let a = if channels >= 4 { data[i + 3] } else { 255 };
+                pixels.push(Color::rgba(data[i], data[i + 1], data[i + 2], a));
+            }
+        }
+        Ok(Texture::new(width, height, pixels))
+    }
+
+    // 整数座標の画素(範囲外はwrapに従う)
+    fn texel(&self, x: i64, y: i64) -> LinearColor {
+        let wrap = |i: i64, n: usize| match self.wrap {
+            Wrap::Repeat => ((i % n as i64 + n as i64) % n as i64) as usize,
+            Wrap::Clamp => i.max(0).min(n as i64 - 1) as usize
+        };
+        self.pixels[wrap(y, self.height) * self.width + wrap(x, self.width)]
+    }
+
+    // テクスチャ座標の色(vは下から上)
+    pub fn sample(&self, uv: &TexCoord) -> LinearColor {
+        let x = uv.u * self.width as f64;
+        let y = (1.0f64 - uv.v) * self.height as f64;
+        match self.filter {
+            Filter::Nearest => self.texel(x.floor() as i64, y.floor() as i64),
+            Filter::Bilinear => {
+                // 画素の中心を基準に補間する
+                let x = x - 0.5f64;
+                let y = y - 0.5f64;
+                let x0 = x.floor();
+                let y0 = y.floor();
+                let fx = x - x0;
+                let fy = y - y0;
+                let (x0, y0) = (x0 as i64, y0 as i64);
+                let c00 = self.texel(x0, y0);
+                let c10 = self.texel(x0 + 1, y0);
+                let c01 = self.texel(x0, y0 + 1);
+                let c11 = self.texel(x0 + 1, y0 + 1);
+                // 線形な値のまま補間する
+                c00.lerp(&c10, fx).lerp(&c01.lerp(&c11, fx), fy)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 黒と白の2x2の市松模様
+    fn checker() -> Texture {
+        let b = Color::new(0, 0, 0);
+        let w = Color::new(255, 255, 255);
+        Texture::new(2, 2, vec![b, w, w, b])
+    }
+
+    #[test]
+    fn nearest() {
+        {
+            let mut t = checker();
+            t.filter = Filter::Nearest;
+            let r = |t: &Texture, u: f64, v: f64| t.sample(&TexCoord::new(u, v)).to_srgb().r;
+            // 左上の画素(vは下から上)
+            assert_eq!(0, r(&t, 0.25f64, 0.75f64));
+            assert_eq!(255, r(&t, 0.75f64, 0.75f64));
+            // 繰り返し
+            assert_eq!(255, r(&t, 1.75f64, 0.75f64));
+            assert_eq!(255, r(&t, -0.25f64, 0.75f64));
+            // 端で止める
+            t.wrap = Wrap::Clamp;
+            assert_eq!(255, r(&t, 1.75f64, 0.75f64));
+            assert_eq!(0, r(&t, -0.25f64, 0.75f64));
+        }
+    }
+
+    #[test]
+    fn bilinear() {
+        {
+            let mut t = checker();
+            let r = |t: &Texture, u: f64, v: f64| t.sample(&TexCoord::new(u, v)).to_srgb().r;
+            // 画素の中心ではその画素の色
+            assert_eq!(0, r(&t, 0.25f64, 0.75f64));
+            // 4画素の中間は線形な値の平均(sRGBでは半分より明るい)
+            assert!((t.sample(&TexCoord::new(0.5f64, 0.5f64)).r - 0.5f64).abs() < 1e-10f64);
+            assert_eq!(188, r(&t, 0.5f64, 0.5f64));
+            // 端で止めると外側は端の画素の色
+            t.wrap = Wrap::Clamp;
+            assert_eq!(0, r(&t, 0.0f64, 1.0f64));
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use util::point3::Point3;
+use util::vector3::Vector3;
+use util::matrix4::Matrix4;
+
+// モデル、ビュー、投影の変換行列をまとめたもの
+// 1フレームに1回作り、頂点ごとの変換は行列1回の掛け算で済ませる
+#[derive(Debug, Copy, Clone)]
+pub struct Transform {
+    pub model: Matrix4,
+    // projection * view * model
+    pub mvp: Matrix4,
+    // 法線用(modelの逆行列の転置)
+    pub normal: Matrix4
+}
+
+impl Transform {
+    pub fn new(model: &Matrix4, view: &Matrix4, projection: &Matrix4) -> Transform {
+        let mvp = &(projection * view) * model;
+        // 拡大・縮小が均一でなくても法線が面に垂直なままになるようにする
+        let normal = model.inverse().map(|m| m.transpose()).unwrap_or(*model);
+        Transform { model: *model, mvp, normal }
+    }
+
+    // モデル座標からワールド座標(光源やカリングの計算用)
+    pub fn to_world(self, p: &Point3) -> Point3 {
+        p.affin(&self.model)
+    }
+
+    // モデル座標の法線をワールド座標に(向きだけなので平行移動は効かない)
+    pub fn normal_to_world(&self, n: &Vector3) -> Vector3 {
+        let v = &self.normal * &Vector3::new(n.x, n.y, n.z, 0.0f64);
+        Vector3::new(v.x, v.y, v.z, 0.0f64)
+    }
+
+    // モデル座標から投影後の同次座標(wで割る前)
+    pub fn to_clip(self, p: &Point3) -> Point3 {
+        p.affin(&self.mvp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_clip() {
+        {
+            // まとめた行列で1回変換しても、順に変換しても同じ
+            let model = &Matrix4::translation(&Vector3::new(1f64, 2f64, 3f64, 0f64)) * &Matrix4::rotation_y(0.5f64);
+            let view = Matrix4::look_at(
+                &Point3::new(0f64, 1f64, -20f64, 1f64),
+                &Point3::new(0f64, 0f64, 0f64, 1f64),
+                &Vector3::new(0f64, 1f64, 0f64, 0f64));
+            let projection = Matrix4::perspective(0.8f64, 1.5f64, 1f64, 100f64);
+            let t = Transform::new(&model, &view, &projection);
+            let p = Point3::new(0.3f64, -0.7f64, 2f64, 1f64);
+            let a = t.to_clip(&p);
+            let b = p.affin(&model).affin(&view).affin(&projection);
+            assert!((a.x - b.x).abs() < 1e-10f64);
+            assert!((a.y - b.y).abs() < 1e-10f64);
+            assert!((a.z - b.z).abs() < 1e-10f64);
+            assert!((a.w - b.w).abs() < 1e-10f64);
+        }
+    }
+
+    #[test]
+    fn normal_to_world() {
+        {
+            // 平行移動と均一でない拡大・縮小をしても、法線は変換後の面に垂直
+            let model = &(&Matrix4::translation(&Vector3::new(5f64, -3f64, 2f64, 0f64)) * &Matrix4::rotation_z(0.3f64))
+                * &Matrix4::scaling(&Vector3::new(1f64, 4f64, 0.5f64, 0f64));
+            let identity = Matrix4::identity();
+            let t = Transform::new(&model, &identity, &identity);
+            let p1 = Point3::new(0f64, 0f64, 0f64, 1f64);
+            let p2 = Point3::new(1f64, 1f64, 0f64, 1f64);
+            let p3 = Point3::new(0f64, 1f64, 1f64, 1f64);
+            let n = &Vector3::from(&p2 - &p1) * &Vector3::from(&p3 - &p1);
+            // 正規化でwが1になった法線でも平行移動されない
+            let n = t.normal_to_world(&n.normalize());
+            let (q1, q2, q3) = (t.to_world(&p1), t.to_world(&p2), t.to_world(&p3));
+            assert!(n.dot(&Vector3::from(&q2 - &q1)).abs() < 1e-10f64);
+            assert!(n.dot(&Vector3::from(&q3 - &q1)).abs() < 1e-10f64);
+            assert_eq!(0f64, n.w);
+            // 面の表側を向いたまま
+            let face = &Vector3::from(&q2 - &q1) * &Vector3::from(&q3 - &q1);
+            assert!(n.dot(&face) > 0f64);
+        }
+    }
+}
@@ -1,7 +1,7 @@
 use std::ops::{Add, Sub, Mul, Div};
 use util::point3::Point3;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Vector3 {
     pub x: f64,
     pub y: f64,
@@ -26,6 +26,11 @@ impl Vector3 {
     pub fn normalize(&self) -> Vector3 {
         Vector3::new(self.x / self.norm(), self.y / self.norm(), self.z / self.norm(), 1.0f64)
     }
+    // 各要素の差がeps以下か
+    pub fn approx_eq(&self, rhs: &Vector3, eps: f64) -> bool {
+        (self.x - rhs.x).abs() <= eps && (self.y - rhs.y).abs() <= eps &&
+            (self.z - rhs.z).abs() <= eps && (self.w - rhs.w).abs() <= eps
+    }
 }
 
 // Point3<T>からVector3<T>への変換
@@ -48,7 +53,7 @@ impl<'a> Add<&'a Vector3> for &'a Vector3 {
     type Output = Vector3;
 
     // ベクトル同士の加算
-    fn add<'b>(self, rhs: &'b Vector3) -> Vector3 {
+    fn add(self, rhs: &Vector3) -> Vector3 {
         Vector3::new(self.x + rhs.x, self.y + rhs.y, self.z + rhs.z, 1.0f64)
     }
 }
@@ -57,7 +62,7 @@ impl<'a> Sub<&'a Vector3> for &'a Vector3 {
     type Output = Vector3;
 
     // ベクトル同士の減算
-    fn sub<'b>(self, rhs: &'b Vector3) -> Vector3 {
+    fn sub(self, rhs: &Vector3) -> Vector3 {
         Vector3::new(self.x - rhs.x, self.y - rhs.y, self.z - rhs.z, 1.0f64)
     }
 }
@@ -66,7 +71,7 @@ impl<'a> Add<&'a Point3> for &'a Vector3 {
     type Output = Vector3;
 
     // ベクトルと座標の加算
-    fn add<'b>(self, rhs: &'b Point3) -> Vector3 {
+    fn add(self, rhs: &Point3) -> Vector3 {
         Vector3::new(self.x + rhs.x, self.y + rhs.y, self.z + rhs.z, 1.0f64)
     }
 }
@@ -75,12 +80,12 @@ impl<'a> Sub<&'a Point3> for &'a Vector3 {
     type Output = Vector3;
 
     // ベクトルと座標の減算
-    fn sub<'b>(self, rhs: &'b Point3) -> Vector3 {
+    fn sub(self, rhs: &Point3) -> Vector3 {
         Vector3::new(self.x - rhs.x, self.y - rhs.y, self.z - rhs.z, 1.0f64)
     }
 }
 
-impl<'a> Mul<f64> for &'a Vector3 {
+impl Mul<f64> for &Vector3 {
     type Output = Vector3;
 
     // スカラ値の乗算
@@ -89,7 +94,7 @@ impl<'a> Mul<f64> for &'a Vector3 {
     }
 }
 
-impl<'a> Div<f64> for &'a Vector3 {
+impl Div<f64> for &Vector3 {
     type Output = Vector3;
 
     // スカラ値の除算
@@ -102,7 +107,7 @@ impl<'a> Mul<&'a Vector3> for &'a Vector3 {
     type Output = Vector3;
 
     // 外積
-    fn mul<'b>(self, rhs: &'b Vector3) -> Vector3 {
+    fn mul(self, rhs: &Vector3) -> Vector3 {
         Vector3::new(
             self.y * rhs.z - self.z * rhs.y,
             self.z * rhs.x - self.x * rhs.z,
@@ -125,9 +130,9 @@ import numpy as np
 5.3851648071345037
 */
             // 長さ
-            let a = Vector3::new(2f32, 3f32, 4f32, 1f32);
+            let a = Vector3::new(2f64, 3f64, 4f64, 1f64);
             let norm = a.norm();
-            assert!(norm - 5.3851648071345037f32 < 1e-10f32);
+            assert!((norm - 5.385164807134504f64).abs() < 1e-10f64);
         }
     }
     #[test]
@@ -140,13 +145,12 @@ import numpy as np
 array([ 0.37139068,  0.55708601,  0.74278135])
 */
             // 単位ベクトル
-            let a = Vector3::new(2f32, 3f32, 4f32, 1f32);
+            let a = Vector3::new(2f64, 3f64, 4f64, 1f64);
             let b = a.normalize();
-            println!("{:?}", b);
-            assert!(b.x - 0.37139068f32 < 1e-5f32);
-            assert!(b.y - 0.55708601f32 < 1e-5f32);
-            assert!(b.z - 0.74278135f32 < 1e-5f32);
-            //assert!(b.w - 0.18257419f32 < 1e-5f32);
+            assert!((b.x - 0.37139068f64).abs() < 1e-5f64);
+            assert!((b.y - 0.55708601f64).abs() < 1e-5f64);
+            assert!((b.z - 0.74278135f64).abs() < 1e-5f64);
+            assert!((b.norm() - 1f64).abs() < 1e-10f64);
         }
     }
 
@@ -161,10 +165,10 @@ import numpy as np
 56
 */
             // 内積
-            let a = Vector3::new(2f32, 3f32, 4f32, 1f32);
-            let b = Vector3::new(5f32, 6f32, 7f32, 1f32);
-            let dot = a.dot(b);
-            assert_eq!(56f32, dot);
+            let a = Vector3::new(2f64, 3f64, 4f64, 1f64);
+            let b = Vector3::new(5f64, 6f64, 7f64, 1f64);
+            let dot = a.dot(&b);
+            assert_eq!(56f64, dot);
         }
     }
 
@@ -183,10 +187,61 @@ array([[18, 21, 24],
        [30, 35, 40]])
 */
             // 外積
-            let a = Vector3::new(3f32, 4f32, 5f32, 1f32);
-            let b = Vector3::new(6f32, 7f32, 8f32, 1f32);
-            let cp = a * b;
-            assert_eq!(Vector3::new(-3f32, 6f32, -3f32, 1f32), cp);
+            let a = Vector3::new(3f64, 4f64, 5f64, 1f64);
+            let b = Vector3::new(6f64, 7f64, 8f64, 1f64);
+            let cp = &a * &b;
+            assert_eq!(Vector3::new(-3f64, 6f64, -3f64, 1f64), cp);
+        }
+    }
+
+    #[test]
+    fn add() {
+        {
+            // ベクトル同士、ベクトルと座標の加算
+            let a = Vector3::new(1f64, 2f64, 3f64, 1f64);
+            let b = Vector3::new(2f64, 3f64, 4f64, 1f64);
+            let c = Point3::new(2f64, 3f64, 4f64, 1f64);
+            assert_eq!(Vector3::new(3f64, 5f64, 7f64, 1f64), &a + &b);
+            assert_eq!(Vector3::new(3f64, 5f64, 7f64, 1f64), &a + &c);
+        }
+    }
+
+    #[test]
+    fn sub() {
+        {
+            // ベクトル同士、ベクトルと座標の減算
+            let a = Vector3::new(1f64, 2f64, 3f64, 1f64);
+            let b = Vector3::new(2f64, 3f64, 4f64, 1f64);
+            let c = Point3::new(2f64, 3f64, 4f64, 1f64);
+            assert_eq!(Vector3::new(-1f64, -1f64, -1f64, 1f64), &a - &b);
+            assert_eq!(Vector3::new(-1f64, -1f64, -1f64, 1f64), &a - &c);
+        }
+    }
+
+    #[test]
+    fn mul_div() {
+        {
+            // スカラ値の乗算、除算
+            let a = Vector3::new(1f64, 2f64, 3f64, 1f64);
+            assert_eq!(Vector3::new(2f64, 4f64, 6f64, 1f64), &a * 2f64);
+            assert_eq!(Vector3::new(0.5f64, 1f64, 1.5f64, 1f64), &a / 2f64);
+        }
+    }
+
+    #[test]
+    fn from() {
+        {
+            let a = Point3::new(1f64, 2f64, 3f64, 4f64);
+            assert_eq!(Vector3::new(1f64, 2f64, 3f64, 4f64), Vector3::from(a));
+        }
+    }
+
+    #[test]
+    fn approx_eq() {
+        {
+            let a = Vector3::new(1f64, 2f64, 3f64, 1f64);
+            assert!(a.approx_eq(&Vector3::new(1f64, 2f64 - 1e-12f64, 3f64, 1f64), 1e-10f64));
+            assert!(!a.approx_eq(&Vector3::new(1f64, 2f64, 3f64, 0f64), 1e-10f64));
         }
     }
 }
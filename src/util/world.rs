@@ -1,97 +1,259 @@
-extern crate gio;
-extern crate gtk;
 extern crate cairo;
 
-use std::cmp::Ordering;
 use std::f64::consts::PI;
-use gio::prelude::*;
-use gtk::prelude::*;
+use std::cmp::Ordering;
 
 use util::screen::Screen;
 use util::color::Color;
+use util::linear_color::LinearColor;
 use util::size2::Size2;
 use util::point3::Point3;
 use util::vector3::Vector3;
 use util::polygon3::Polygon3;
-use util::camera::Camera;
-use util::light::Light;
+use util::quaternion::Quaternion;
+use util::mesh::Mesh;
+use util::tex_coord::TexCoord;
+use util::framebuffer::FrameBuffer;
+use util::rasterizer;
+use util::clipper;
+use util::camera::{Camera, Projection};
+use util::light::{Light, Lighting, Attenuation};
+
+// 陰影の付け方
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Shading {
+    // 面ごとに1色
+    Flat,
+    // 頂点ごとに光を計算して面の中で補間する
+    Gouraud,
+    // 位置と法線を補間して画素ごとに光を計算する
+    Phong
+}
 
 #[derive(Debug)]
 pub struct World {
-    pub polygons: Vec<(Polygon3, Color)>,
+    pub mesh: Mesh,
     pub screen: Screen,
     pub camera: Camera,
-    pub light: Light
+    pub lighting: Lighting,
+    pub shading: Shading,
+    pub background: Color
 }
 
 impl World {
-    pub fn new(polygons: &Vec<(Polygon3, Color)>, width: f64, height: f64, scale: f64) -> World {
-        let rotate = 0i32;
-
+    pub fn new(mesh: Mesh, width: f64, height: f64, fov: f64) -> World {
         // スクリーン(画面)
-        let screen = Screen::new(&Size2::new(width, height), scale);
+        let screen = Screen::new(&Size2::new(width, height));
         // カメラ
         let position = Point3::new(0.0f64, -1.0f64, -100.0f64, 1.0f64);
         let look_at = Point3::new(0.0f64, -1.0f64, 0.0f64, 1.0f64);
         let up = Vector3::new(0.0f64, 1.0f64, 0.0f64, 1.0f64);
         let near = 10.0f64;
         let far = 300.0f64;
-        let camera = Camera::new(&position, &look_at, &up, near, far);
-        // 光源
-        let light = Light::new(&Point3::new(-500.0f64, 500.0f64, -500.0f64, 1.0f64));
+        let camera = Camera::new(&position, &look_at, &up, Projection::Perspective { fov }, width / height, near, far);
+        // 光源(弱い環境光と左上手前の白い点光源)
+        // 点光源は距離の2乗で弱まり、原点(約866離れている)で0.9くらいの明るさになる
+        let white = LinearColor::new(1.0f64, 1.0f64, 1.0f64, 1.0f64);
+        let lighting = Lighting::new(vec![
+            Light::ambient(&white, 0.1f64),
+            Light::point(&Point3::new(-500.0f64, 500.0f64, -500.0f64, 1.0f64),
+                         &Attenuation::new(1.0f64, 0.0f64, 1.0e-6f64), &white, 1.6f64)]);
 
-        World { polygons: polygons.clone(), screen, camera, light }
+        // 背景色
+        let background = Color::new(0, 0, 0);
+
+        World { mesh, screen, camera, lighting, shading: Shading::Gouraud, background }
+    }
+
+    // 画面の大きさが変わった
+    pub fn resize(&mut self, width: f64, height: f64) {
+        self.screen.size = Size2::new(width, height);
+        self.camera.aspect = width / height;
     }
 
-    pub fn draw(&self, rotate: i32, cr: &cairo::Context) -> () {
+    // モデル全体が画面のfraction(0.0〜1.0)に収まるようにカメラを置く
+    // モデルは原点を中心にしておくこと
+    pub fn fit_to_view(&mut self, fraction: f64) {
+        let radius = self.mesh.radius();
+        if radius <= 0.0f64 || fraction <= 0.0f64 {
+            return;
+        }
+        // 見ている方向は変えずに原点からの距離を決める
+        let direction = self.camera.direction().normalize();
+        let aspect = self.camera.aspect.min(1.0f64);
+        let distance = match self.camera.projection {
+            // 縦横の狭い方の視野角に、モデルを包む球がfractionの大きさで収まる距離
+            Projection::Perspective { fov } =>
+                radius / (fraction * (fov / 2.0f64).tan() * aspect).atan().sin(),
+            // 見える範囲の縦横の狭い方の半分がradius / fractionになるようにする
+            Projection::Orthographic { ref mut height } => {
+                *height = 2.0f64 * radius / fraction / aspect;
+                radius * 3.0f64
+            }
+        };
+        self.camera.look_at = Point3::new(0.0f64, 0.0f64, 0.0f64, 1.0f64);
+        self.camera.position = &self.camera.look_at - &(&direction * distance);
+        self.fit_clip_planes();
+    }
+
+    // 今のカメラの位置からモデル全体が切り取られないように手前と奥の面を決める
+    // カメラを動かしたあとに呼ぶ(モデルは原点を中心にしておくこと)
+    pub fn fit_clip_planes(&mut self) {
+        let radius = self.mesh.radius();
+        let distance = Vector3::from(self.camera.position).norm();
+        self.camera.near = ((distance - radius) * 0.5f64).max(distance.max(radius) * 0.01f64);
+        self.camera.far = distance + radius * 2.0f64;
+    }
+
+    pub fn draw(&self, rotate: i32, cr: &cairo::Context) {
         let r = 0.0f64;
         cr.save();
-        // ？？？
-        cr.scale(0.8f64, 0.8f64);
-        cr.translate(0.0f64, self.screen.size.height / 1.3f64);
         // 背景を塗る
-        cr.set_source_rgb(0.0f64, 0.0f64, 0.0f64);
+        cr.set_source_rgb(
+            f64::from(self.background.r) / 255.0f64,
+            f64::from(self.background.g) / 255.0f64,
+            f64::from(self.background.b) / 255.0f64);
         cr.paint();
-        // デバッグ用
-        let recs = cr.copy_clip_rectangle_list();
-        let c = recs.rectangles.len();
-        for i in 0..c {
-            recs.rectangles.get(i).map(|rec|
-                println!("x = {}, y = {}, width = {}, height = {}", rec.x, rec.y, rec.width, rec.height)
-            );
-        }
-        // ポリゴン群を投影面の座標に変換
-        let mut v1: Vec<(Polygon3, Color)> = self.polygons.iter()
-            .map(|&(p, c)|
-                // 回転
-                (p.rotate_x(r / 360.0f64 * 2.0f64 * PI)
-                  .rotate_y(f64::from(rotate) / 360.0f64 * 2.0f64 * PI), c))
-            // 拡散光の計算
-            .map(|(p, c)| (p, self.light.get_diffuse_color(&c, &p)))
+        // 回転、ビューポート変換、射影変換の行列はフレームごとに1回だけまとめる
+        let rotation = Quaternion::from_euler(r / 360.0f64 * 2.0f64 * PI, f64::from(rotate) / 360.0f64 * 2.0f64 * PI, 0.0f64)
+            .to_matrix();
+        let transform = self.camera.transform(&rotation);
+        // 光源とカリングはワールド座標で計算する
+        let world_positions: Vec<Point3> = self.mesh.vertices.iter()
+            .map(|v| transform.to_world(&v.position))
+            .collect();
+        let world_normals: Vec<Option<Vector3>> = self.mesh.vertices.iter()
+            .map(|v| v.normal.map(|n| transform.normal_to_world(&n)))
+            .collect();
+        // 投影後の座標は頂点ごとに1回の掛け算で求める
+        let projected: Vec<Point3> = self.mesh.vertices.iter()
+            .map(|v| transform.to_clip(&v.position))
+            .collect();
+        // 見える範囲(wで割る前に切り取る)
+        let planes = self.camera.clip_planes();
+        // 三角形ごとの処理は添字から組み立てる
+        let polygon = |ps: &Vec<Point3>, indices: &[usize; 3]|
+            Polygon3::new(&ps[indices[0]], &ps[indices[1]], &ps[indices[2]]);
+        let mut v1: Vec<(&[usize; 3], usize, Polygon3, [Vector3; 3])> = self.mesh.triangles.iter()
+            .map(|t| (&t.indices, t.material, polygon(&world_positions, &t.indices)))
             // カリング(カメラから見て裏面のポリゴンは省略)
-            .filter(|&(p, c)| !self.camera.is_cull(p))
-            // ビューポート変換
-            .map(|(p, c)| (self.camera.convert_to_view(p), c))
+            .filter(|&(_, _, p)| !self.camera.is_cull(p))
+            // 光の計算に使う法線
+            .map(|(indices, m, p)| match self.shading {
+                // 頂点の法線があればその平均を使う
+                Shading::Flat => {
+                    let normal = indices.iter()
+                        .map(|&i| world_normals[i])
+                        .try_fold(Vector3::new(0.0f64, 0.0f64, 0.0f64, 0.0f64), |sum, n| n.map(|n| &sum + &n))
+                        .unwrap_or_else(|| p.normal());
+                    (indices, m, p, [normal, normal, normal])
+                }
+                // 頂点ごと(法線がなければ面の法線)
+                Shading::Gouraud | Shading::Phong => {
+                    let normal = |i: usize| world_normals[i].unwrap_or_else(|| p.normal());
+                    (indices, m, p, [normal(indices[0]), normal(indices[1]), normal(indices[2])])
+                }
+            })
             .collect();
-        // 奥からソート
-        v1.sort_by(|&(l, _), &(r, _)| {
-            let _l = l.p1.z + l.p2.z + l.p3.z;
-            let _r = r.p1.z + r.p2.z + r.p3.z;
-            if _l - _r < 1e-10f64 { Ordering::Equal } else if _l < _r { Ordering::Greater } else { Ordering::Less }
+        // 描画(表示されている範囲を画素単位で塗り、Zバッファで前後を判定する)
+        let (x1, y1, x2, y2) = cr.clip_extents();
+        let mut fb = FrameBuffer::new((x2 - x1).ceil().max(0.0f64) as usize, (y2 - y1).ceil().max(0.0f64) as usize);
+        fb.clear(&self.background);
+        let origin = Vector3::new(x1, y1, 0.0f64, 0.0f64);
+        let to_screen = |p: &Point3| &self.screen.convert_point_to_screen(&self.camera.perspective_point(p)) - &origin;
+        // 見える範囲の内側の頂点はスクリーン座標も頂点ごとに1回だけ求める
+        let screen_positions: Vec<Option<Point3>> = projected.iter()
+            .map(|p| if clipper::is_inside(p, &planes) { Some(to_screen(p)) } else { None })
+            .collect();
+        // 半透明(dが1未満)の面は不透明な面を描いた後に奥から順に重ねる
+        let translucent = |m: usize| self.mesh.materials[m].dissolve < 1.0f64;
+        let depth = |p: &Polygon3| Vector3::from(&p.center() - &self.camera.position).dot(&self.camera.direction());
+        v1.sort_by(|a, b| match (translucent(a.1), translucent(b.1)) {
+            (true, true) => depth(&b.2).partial_cmp(&depth(&a.2)).unwrap_or(Ordering::Equal),
+            (x, y) => x.cmp(&y)
         });
-        v1.iter()
-            // 射影変換
-            .map(|&(p, c)| (self.camera.projection(&p, &self.screen), c))
-            // 遠近感
-            .filter(|&(p, c)|
-                p.p1.z >= self.camera.near && p.p1.z <= self.camera.far &&
-                p.p2.z >= self.camera.near && p.p2.z <= self.camera.far &&
-                p.p3.z >= self.camera.near && p.p3.z <= self.camera.far)
-            .map(|(p, c)| (self.camera.perspective(&p), c))
-            // スクリーン変換
-            .map(|(p, c)| (self.screen.convert_to_screen(&p), c))
-            // 描画
-            .for_each(|(p, c)| p.draw(cr, &c));
+        for &(indices, m, p, normals) in v1.iter() {
+            let material = &self.mesh.materials[m];
+            let uvs: Vec<Option<TexCoord>> = indices.iter()
+                .map(|&i| self.mesh.vertices[i].tex_coord)
+                .collect();
+            let points = [world_positions[indices[0]], world_positions[indices[1]], world_positions[indices[2]]];
+            // 全ての光源の拡散光と鏡面反射の強さ
+            let level = |position: &Point3, normal: &Vector3|
+                self.lighting.get_levels(position, normal, &self.camera.to_eye(position), material.shininess);
+            let levels = match self.shading {
+                // 面の重心で計算する
+                Shading::Flat => {
+                    let l = level(&p.center(), &normals[0]);
+                    [l, l, l]
+                }
+                Shading::Gouraud => [level(&points[0], &normals[0]), level(&points[1], &normals[1]), level(&points[2], &normals[2])],
+                // 画素ごとに計算するので使わない
+                Shading::Phong => [(LinearColor::black(), LinearColor::black()); 3]
+            };
+            let clip = [projected[indices[0]], projected[indices[1]], projected[indices[2]]];
+            let cached = [screen_positions[indices[0]], screen_positions[indices[1]], screen_positions[indices[2]]];
+            // 3点とも内側なら切り取られずにそのまま返ってくる
+            let whole = cached.iter().all(|p| p.is_some());
+            // 切り取った結果の三角形ごとに塗る
+            for t in clipper::clip_triangle(&clip, &planes).iter() {
+                // スクリーン変換(切り取られなかった三角形は頂点ごとに求めたものを使う)
+                let screen = |i: usize| match cached[i] {
+                    Some(p) if whole => p,
+                    _ => to_screen(&t[i].position)
+                };
+                let positions = [screen(0), screen(1), screen(2)];
+                let ws = [t[0].position.w, t[1].position.w, t[2].position.w];
+                // 切り取った三角形の重心座標を元の三角形の重心座標に直す
+                let original = |b: &[f64; 3]| {
+                    let mut result = [0.0f64; 3];
+                    for (i, r) in result.iter_mut().enumerate() {
+                        *r = t[0].barycentric[i] * b[0] + t[1].barycentric[i] * b[1] + t[2].barycentric[i] * b[2];
+                    }
+                    result
+                };
+                // 元の三角形の重心座標での色(線形な値で計算し、画素に書くときにsRGBに戻す)
+                let shade = |color: &LinearColor, b: &[f64; 3]| match self.shading {
+                    // 位置と法線を補間して光を計算する
+                    Shading::Phong => {
+                        let position = Point3::new(
+                            points[0].x * b[0] + points[1].x * b[1] + points[2].x * b[2],
+                            points[0].y * b[0] + points[1].y * b[1] + points[2].y * b[2],
+                            points[0].z * b[0] + points[1].z * b[1] + points[2].z * b[2],
+                            1.0f64);
+                        let normal = Vector3::new(
+                            normals[0].x * b[0] + normals[1].x * b[1] + normals[2].x * b[2],
+                            normals[0].y * b[0] + normals[1].y * b[1] + normals[2].y * b[2],
+                            normals[0].z * b[0] + normals[1].z * b[1] + normals[2].z * b[2],
+                            0.0f64);
+                        self.lighting.shade(color, material, &position, &normal, &self.camera.to_eye(&position)).to_srgb()
+                    }
+                    // 光の強さを補間する(Flatなら3つとも同じ)
+                    _ => {
+                        let mix = |l0: &LinearColor, l1: &LinearColor, l2: &LinearColor|
+                            &(&(l0 * b[0]) + &(l1 * b[1])) + &(l2 * b[2]);
+                        let d = mix(&levels[0].0, &levels[1].0, &levels[2].0);
+                        let s = mix(&levels[0].1, &levels[1].1, &levels[2].1);
+                        self.lighting.apply(color, material, &d, &s).to_srgb()
+                    }
+                };
+                let blend = translucent(m);
+                match (&material.texture, uvs[0], uvs[1], uvs[2]) {
+                    // テクスチャ座標を補間して画素ごとに色を取り、Kdを掛ける
+                    (Some(texture), Some(t0), Some(t1), Some(t2)) =>
+                        rasterizer::fill_triangle(&mut fb, &positions, &ws, blend, |b| {
+                            let b = original(b);
+                            let uv = TexCoord::new(
+                                t0.u * b[0] + t1.u * b[1] + t2.u * b[2],
+                                t0.v * b[0] + t1.v * b[1] + t2.v * b[2]);
+                            shade(&(&texture.sample(&uv) * &material.diffuse), &b)
+                        }),
+                    _ => rasterizer::fill_triangle(&mut fb, &positions, &ws, blend, |b|
+                        shade(&material.diffuse, &original(b)))
+                }
+            }
+        }
+        fb.draw(cr, x1, y1);
         cr.restore();
     }
 }